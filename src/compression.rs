@@ -0,0 +1,225 @@
+//! Support for clickhouse's native compressed block format.
+//!
+//! When `compress=1` is passed to the HTTP interface, clickhouse sends (and
+//! accepts) data as a sequence of blocks, each of which looks like:
+//!
+//! ```text
+//! checksum: 16 bytes (CityHash128 v1.0.2 of everything that follows)
+//! method: 1 byte (0x82 for LZ4, 0x02 for no compression)
+//! compressed_size: u32 little-endian (includes the 9 header bytes)
+//! decompressed_size: u32 little-endian
+//! data: compressed_size - 9 bytes
+//! ```
+
+use crate::error::find_exception;
+use crate::Error;
+use futures_util::stream::{try_unfold, TryStreamExt};
+
+const CHECKSUM_SIZE: usize = 16;
+const HEADER_SIZE: usize = 9;
+/// clickhouse never creates blocks anywhere near this large, so a larger size
+/// means the data is corrupt and we should not try to allocate it.
+const MAX_BLOCK_SIZE: usize = 1 << 30;
+
+/// The size of the uncompressed blocks we send, which matches the buffer
+/// size clickhouse itself uses.
+pub(crate) const MAX_UNCOMPRESSED_BLOCK_SIZE: usize = 1 << 20;
+
+const METHOD_NONE: u8 = 0x02;
+const METHOD_LZ4: u8 = 0x82;
+
+fn checksum(data: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let hash = cityhash_rs::cityhash_102_128(data);
+    let mut out = [0; CHECKSUM_SIZE];
+    out[..8].copy_from_slice(&((hash >> 64) as u64).to_le_bytes());
+    out[8..].copy_from_slice(&(hash as u64).to_le_bytes());
+    out
+}
+
+/// Compress data into a sequence of LZ4 blocks.
+pub(crate) fn compress_lz4(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() / 2);
+    for chunk in data.chunks(MAX_UNCOMPRESSED_BLOCK_SIZE) {
+        let compressed = lz4_flex::block::compress(chunk);
+        let start = out.len();
+        out.extend([0; CHECKSUM_SIZE]);
+        out.push(METHOD_LZ4);
+        out.extend(((compressed.len() + HEADER_SIZE) as u32).to_le_bytes());
+        out.extend((chunk.len() as u32).to_le_bytes());
+        out.extend(compressed);
+        let sum = checksum(&out[start + CHECKSUM_SIZE..]);
+        out[start..start + CHECKSUM_SIZE].copy_from_slice(&sum);
+    }
+    out
+}
+
+/// An incremental decoder for a sequence of compressed blocks.
+///
+/// Bytes are [`push`](Lz4Decoder::push)ed as they arrive, and each block is
+/// decoded as soon as it has been entirely received.
+#[derive(Default)]
+pub(crate) struct Lz4Decoder {
+    buffer: Vec<u8>,
+}
+
+impl Lz4Decoder {
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Returns true if there is no partially received block.
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Returns true if the server has sent an exception in place of the next
+    /// block.
+    pub fn is_exception(&self) -> bool {
+        find_exception(&self.buffer) == Some(0)
+    }
+
+    /// Decode the next block, if it has been completely received.
+    pub fn next_block(&mut self) -> Result<Option<Vec<u8>>, Error> {
+        if self.buffer.len() < CHECKSUM_SIZE + HEADER_SIZE {
+            return Ok(None);
+        }
+        let header = &self.buffer[CHECKSUM_SIZE..CHECKSUM_SIZE + HEADER_SIZE];
+        let method = header[0];
+        let compressed_size = u32::from_le_bytes(header[1..5].try_into().unwrap()) as usize;
+        let decompressed_size = u32::from_le_bytes(header[5..9].try_into().unwrap()) as usize;
+        if !(HEADER_SIZE..=MAX_BLOCK_SIZE).contains(&compressed_size) {
+            return Err(Error::Decompression(format!(
+                "invalid compressed block size {compressed_size}"
+            )));
+        }
+        if decompressed_size > MAX_BLOCK_SIZE {
+            return Err(Error::Decompression(format!(
+                "invalid decompressed block size {decompressed_size}"
+            )));
+        }
+        if self.buffer.len() < CHECKSUM_SIZE + compressed_size {
+            return Ok(None);
+        }
+
+        let block = &self.buffer[CHECKSUM_SIZE..CHECKSUM_SIZE + compressed_size];
+        if checksum(block) != self.buffer[..CHECKSUM_SIZE] {
+            return Err(Error::Decompression("checksum mismatch".to_string()));
+        }
+        let data = &block[HEADER_SIZE..];
+        let out = match method {
+            METHOD_LZ4 => lz4_flex::block::decompress(data, decompressed_size)
+                .map_err(|e| Error::Decompression(e.to_string()))?,
+            METHOD_NONE => data.to_vec(),
+            _ => {
+                return Err(Error::Decompression(format!(
+                    "unsupported compression method 0x{method:02x}"
+                )))
+            }
+        };
+        if out.len() != decompressed_size {
+            return Err(Error::Decompression(format!(
+                "expected {decompressed_size} bytes but decompressed {}",
+                out.len()
+            )));
+        }
+        self.buffer.drain(..CHECKSUM_SIZE + compressed_size);
+        Ok(Some(out))
+    }
+}
+
+/// Decompress a response body as its blocks arrive.
+pub(crate) fn decompress_body(
+    body: hyper::Body,
+) -> impl futures_util::Stream<Item = Result<hyper::body::Bytes, Error>> + Send {
+    try_unfold(
+        (body, Lz4Decoder::default()),
+        |(mut body, mut decoder)| async move {
+            loop {
+                if decoder.is_exception() {
+                    // The exception is sent uncompressed, and ends the body.
+                    while let Some(bytes) = body.try_next().await? {
+                        decoder.push(&bytes);
+                    }
+                    return Err(Error::from_exception(&decoder.buffer));
+                }
+                if let Some(block) = decoder.next_block()? {
+                    return Ok(Some((hyper::body::Bytes::from(block), (body, decoder))));
+                }
+                match body.try_next().await? {
+                    Some(bytes) => decoder.push(&bytes),
+                    None if decoder.is_empty() => return Ok(None),
+                    None => {
+                        return Err(Error::Decompression(
+                            "response ended in the middle of a block".to_string(),
+                        ))
+                    }
+                }
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_incrementally() {
+        let first = b"hello world, hello world, hello world".to_vec();
+        let second = (0..1000u32)
+            .flat_map(|i| i.to_le_bytes())
+            .collect::<Vec<_>>();
+        let mut encoded = compress_lz4(&first);
+        encoded.extend(compress_lz4(&second));
+
+        let mut decoder = Lz4Decoder::default();
+        let mut blocks = Vec::new();
+        for b in encoded.chunks(7) {
+            decoder.push(b);
+            while let Some(block) = decoder.next_block().unwrap() {
+                blocks.push(block);
+            }
+        }
+        assert!(decoder.is_empty());
+        assert_eq!(vec![first, second], blocks);
+    }
+
+    #[test]
+    fn corrupt_checksum() {
+        let mut encoded = compress_lz4(b"hello world");
+        encoded[3] ^= 1;
+        let mut decoder = Lz4Decoder::default();
+        decoder.push(&encoded);
+        assert!(matches!(decoder.next_block(), Err(Error::Decompression(_))));
+    }
+
+    #[test]
+    fn exception_after_block() {
+        let mut encoded = compress_lz4(b"hello world");
+        encoded.extend(b"Code: 241. DB::Exception: Memory limit exceeded\n");
+        let mut decoder = Lz4Decoder::default();
+        decoder.push(&encoded);
+        assert!(!decoder.is_exception());
+        assert_eq!(
+            b"hello world".to_vec(),
+            decoder.next_block().unwrap().unwrap()
+        );
+        assert!(decoder.is_exception());
+    }
+
+    #[test]
+    fn round_trip_large() {
+        let data = (0..MAX_UNCOMPRESSED_BLOCK_SIZE as u64)
+            .flat_map(|i| (i % 1001).to_le_bytes())
+            .collect::<Vec<_>>();
+        let mut decoder = Lz4Decoder::default();
+        decoder.push(&compress_lz4(&data));
+        let mut decoded = Vec::new();
+        while let Some(block) = decoder.next_block().unwrap() {
+            assert!(block.len() <= MAX_UNCOMPRESSED_BLOCK_SIZE);
+            decoded.extend(block);
+        }
+        assert!(decoder.is_empty());
+        assert_eq!(data, decoded);
+    }
+}
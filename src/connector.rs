@@ -0,0 +1,123 @@
+//! Support for custom transports.
+//!
+//! Any connector that hyper can use (i.e. any
+//! [`hyper::client::connect::Connect`] implementation) can be given to
+//! [`ClientBuilder::with_connector`](crate::ClientBuilder::with_connector).
+//! This makes it possible to connect through a proxy, or to an in-memory
+//! transport in tests.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_util::future::BoxFuture;
+use hyper::client::connect::{Connected, Connection};
+use hyper::Uri;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tower_service::Service;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// A connection made by a custom connector.
+trait Io: AsyncRead + AsyncWrite + Connection + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Connection + Unpin + Send> Io for T {}
+
+/// A type-erased connection.
+pub(crate) struct BoxedIo(Box<dyn Io>);
+
+impl Connection for BoxedIo {
+    fn connected(&self) -> Connected {
+        self.0.connected()
+    }
+}
+
+impl AsyncRead for BoxedIo {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut *self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for BoxedIo {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut *self.0).poll_write(cx, buf)
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut *self.0).poll_flush(cx)
+    }
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut *self.0).poll_shutdown(cx)
+    }
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut *self.0).poll_write_vectored(cx, bufs)
+    }
+    fn is_write_vectored(&self) -> bool {
+        self.0.is_write_vectored()
+    }
+}
+
+/// A connector with its concrete type erased.
+trait Connect: Send + Sync {
+    fn connect(&self, uri: Uri) -> BoxFuture<'static, Result<BoxedIo, BoxError>>;
+}
+
+impl<S> Connect for S
+where
+    S: Service<Uri> + Clone + Send + Sync + 'static,
+    S::Response: AsyncRead + AsyncWrite + Connection + Unpin + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<BoxError>,
+{
+    fn connect(&self, uri: Uri) -> BoxFuture<'static, Result<BoxedIo, BoxError>> {
+        let mut connector = self.clone();
+        Box::pin(async move {
+            futures_util::future::poll_fn(|cx| connector.poll_ready(cx))
+                .await
+                .map_err(Into::into)?;
+            let io = connector.call(uri).await.map_err(Into::into)?;
+            Ok(BoxedIo(Box::new(io)))
+        })
+    }
+}
+
+/// The connector used by a [`Client`](crate::Client).
+#[derive(Clone)]
+pub(crate) struct BoxConnector(Arc<dyn Connect>);
+
+impl BoxConnector {
+    pub(crate) fn new<S>(connector: S) -> Self
+    where
+        S: Service<Uri> + Clone + Send + Sync + 'static,
+        S::Response: AsyncRead + AsyncWrite + Connection + Unpin + Send + 'static,
+        S::Future: Send + 'static,
+        S::Error: Into<BoxError>,
+    {
+        BoxConnector(Arc::new(connector))
+    }
+}
+
+impl Service<Uri> for BoxConnector {
+    type Response = BoxedIo;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<BoxedIo, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        self.0.connect(uri)
+    }
+}
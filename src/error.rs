@@ -1,5 +1,7 @@
 use std::error::Error as StdError;
 
+use crate::ErrorCode;
+
 /// Represents all possible errors.
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
@@ -13,6 +15,8 @@ pub enum Error {
     InvalidUnicode(#[from] std::string::FromUtf8Error),
     #[error("no rows returned by a query that expected to return at least one row")]
     RowNotFound,
+    #[error("more than one row returned by a query that expected at most one row")]
+    TooManyRows,
     #[error("sequences must have a known size ahead of time")]
     SequenceMustHaveLength,
     #[error("`deserialize_any` is not supported")]
@@ -21,9 +25,30 @@ pub enum Error {
     InvalidTagEncoding(i8),
     #[error("bad response: {0}")]
     BadResponse(String),
+    /// An exception reported by the clickhouse server.
+    ///
+    /// The `http_status` is 200 if the exception happened after the server had
+    /// started sending rows.
+    #[error("server error {name} ({}): {message}", code.code())]
+    ServerError {
+        code: ErrorCode,
+        name: String,
+        message: String,
+        http_status: u16,
+    },
+    /// An HTTP error without a clickhouse exception, such as one from a
+    /// proxy in front of the server.
+    #[error("HTTP status {status}: {message}")]
+    HttpStatus { status: u16, message: String },
+    #[error("unable to decompress response: {0}")]
+    Decompression(String),
     #[error("Unsupported column type: {0}")]
     UnsupportedColumn(String),
-    #[error("Column types mismatch: [{}] vs [{}]", show_types(&schema), show_types(&row))]
+    #[error(
+        "Column types mismatch: [{}] vs [{}]",
+        show_types(schema),
+        show_types(row)
+    )]
     WrongColumnTypes {
         schema: Vec<String>,
         row: Vec<String>,
@@ -35,6 +60,14 @@ pub enum Error {
     },
     #[error("Each column must have a name: {row:?}")]
     MissingColumnName { row: Vec<&'static str> },
+    #[error("invalid identifier: {0:?}")]
+    InvalidIdentifier(String),
+    #[error("table name {0:?} contains a dot: use a TableRef for a table in another database")]
+    DottedTableName(String),
+    #[error("invalid decimal: {0}")]
+    InvalidDecimal(String),
+    #[error("invalid integer: {0}")]
+    InvalidInteger(String),
 
     // Internally handled errors, not part of public API.
     // XXX: move to another error?
@@ -50,6 +83,11 @@ fn show_types(t: &[String]) -> String {
 impl Error {
     pub async fn from_bad_response(response: hyper::Response<hyper::Body>) -> Self {
         let status = response.status();
+        let header_code = response
+            .headers()
+            .get("X-ClickHouse-Exception-Code")
+            .and_then(|code| code.to_str().ok())
+            .and_then(|code| code.trim().parse::<u32>().ok());
         let raw_bytes = match hyper::body::to_bytes(response.into_body()).await {
             Ok(bytes) => bytes,
             Err(err) => return err.into(),
@@ -65,6 +103,306 @@ impl Error {
                 )
             });
 
-        Error::BadResponse(reason)
+        if let Some(error) = Error::from_server_text(&reason, status.as_u16()) {
+            return error;
+        }
+        match header_code {
+            Some(code) => {
+                let code = ErrorCode::from(code);
+                Error::ServerError {
+                    code,
+                    name: code.name().unwrap_or_default().to_string(),
+                    message: reason,
+                    http_status: status.as_u16(),
+                }
+            }
+            None => Error::HttpStatus {
+                status: status.as_u16(),
+                message: reason,
+            },
+        }
+    }
+
+    /// The error for an exception that the server sent in the body of a
+    /// response after it had started sending rows.
+    pub(crate) fn from_exception(text: &[u8]) -> Self {
+        let text = String::from_utf8_lossy(text);
+        let text = text.trim();
+        Error::from_server_text(text, 200).unwrap_or_else(|| Error::BadResponse(text.into()))
+    }
+
+    /// Parse an exception such as
+    /// `Code: 60. DB::Exception: Table t does not exist. (UNKNOWN_TABLE) (version 23.8.1.1)`.
+    fn from_server_text(text: &str, http_status: u16) -> Option<Self> {
+        let rest = text.strip_prefix("Code: ")?;
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        let code = ErrorCode::from(rest[..digits].parse::<u32>().ok()?);
+        let rest = &rest[digits..];
+        let mut message = rest[rest.find("DB::Exception:")? + "DB::Exception:".len()..].trim();
+        if let Some(i) = message.rfind(" (version ") {
+            if message.ends_with(')') {
+                message = message[..i].trim_end();
+            }
+        }
+        let mut name = None;
+        if let Some(i) = message.rfind(" (") {
+            let n = &message[i + 2..];
+            if let Some(n) = n.strip_suffix(')') {
+                if !n.is_empty()
+                    && n.bytes()
+                        .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_')
+                {
+                    name = Some(n.to_string());
+                    message = message[..i].trim_end();
+                }
+            }
+        }
+        Some(Error::ServerError {
+            code,
+            name: name.unwrap_or_else(|| code.name().unwrap_or_default().to_string()),
+            message: message.to_string(),
+            http_status,
+        })
+    }
+
+    /// Returns true if the error is likely to be transient, so that the
+    /// request may succeed if it is repeated.
+    ///
+    /// This is the case for network errors, and for server errors such as
+    /// [`ErrorCode::TooManySimultaneousQueries`], and for a 502, 503 or 504
+    /// status, which a proxy may return while the server is restarting.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Network(err) => !err.is_user() && !err.is_parse(),
+            Error::ServerError {
+                code, http_status, ..
+            } => {
+                matches!(http_status, 502..=504)
+                    || matches!(
+                        code,
+                        ErrorCode::TooManySimultaneousQueries
+                            | ErrorCode::SocketTimeout
+                            | ErrorCode::NetworkError
+                            | ErrorCode::TooManyParts
+                            | ErrorCode::TableIsReadOnly
+                            | ErrorCode::TooFewLiveReplicas
+                            | ErrorCode::KeeperException
+                            | ErrorCode::UnknownStatusOfInsert
+                    )
+            }
+            Error::HttpStatus { status, .. } => matches!(status, 502..=504),
+            _ => false,
+        }
+    }
+
+    /// The code of the exception, if this error was reported by the server.
+    pub fn server_code(&self) -> Option<ErrorCode> {
+        match self {
+            Error::ServerError { code, .. } => Some(*code),
+            _ => None,
+        }
+    }
+}
+
+/// The most bytes that an exception appended to a response, along with any
+/// partial row before it, may take.
+///
+/// clickhouse's exception messages are at most a few KiB, so when more than
+/// this is left to read, the bytes cannot be the end of the body and an
+/// exception, and are treated as rows.
+pub(crate) const MAX_EXCEPTION_LEN: usize = 64 << 10;
+
+/// Find the start of an exception, such as `Code: 241. DB::Exception: ...`,
+/// that clickhouse appends to a response that fails after it has started.
+pub(crate) fn find_exception(bytes: &[u8]) -> Option<usize> {
+    const PREFIX: &[u8] = b"Code: ";
+    const SUFFIX: &[u8] = b". DB::Exception";
+    let mut start = 0;
+    while let Some(i) = bytes[start..]
+        .windows(PREFIX.len())
+        .position(|w| w == PREFIX)
+    {
+        let code = start + i + PREFIX.len();
+        let digits = bytes[code..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if digits > 0 && bytes[code + digits..].starts_with(SUFFIX) {
+            return Some(start + i);
+        }
+        start = code;
+    }
+    None
+}
+
+/// Whether `bytes` start with an exception, or `None` if they are too short
+/// to tell.
+pub(crate) fn starts_with_exception(bytes: &[u8]) -> Option<bool> {
+    const PREFIX: &[u8] = b"Code: ";
+    const SUFFIX: &[u8] = b". DB::Exception";
+    // Compare as much of `expected` as there is of `bytes`.
+    let matches = |bytes: &[u8], expected: &[u8]| {
+        let n = bytes.len().min(expected.len());
+        if bytes[..n] != expected[..n] {
+            Some(false)
+        } else {
+            (n == expected.len()).then_some(true)
+        }
+    };
+    if !matches(bytes, PREFIX)? {
+        return Some(false);
+    }
+    let rest = &bytes[PREFIX.len()..];
+    let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+    match (digits, rest.len()) {
+        (_, n) if n == digits => None,
+        (0, _) => Some(false),
+        _ => matches(&rest[digits..], SUFFIX),
     }
 }
+
+/// The marker that starts an exception when the server sent the
+/// `X-ClickHouse-Exception-Tag` header.
+///
+/// Such an exception is sent as
+/// `__exception__\r\n<tag>\r\n<message>\r\n<length> <tag>\r\n__exception__\r\n`,
+/// where the tag is random, so it cannot be mistaken for row data.
+fn exception_marker(tag: &str) -> Vec<u8> {
+    format!("__exception__\r\n{tag}\r\n").into_bytes()
+}
+
+/// Find the start of an exception that is marked with `tag`.
+pub(crate) fn find_tagged_exception(bytes: &[u8], tag: &str) -> Option<usize> {
+    let marker = exception_marker(tag);
+    bytes.windows(marker.len()).position(|w| w == marker)
+}
+
+/// The message of an exception found by [`find_tagged_exception`].
+pub(crate) fn untag_exception<'a>(bytes: &'a [u8], tag: &str) -> &'a [u8] {
+    let message = &bytes[exception_marker(tag).len().min(bytes.len())..];
+    let end = format!(" {tag}\r\n__exception__");
+    let Some(end) = message
+        .windows(end.len())
+        .rposition(|w| w == end.as_bytes())
+    else {
+        return message;
+    };
+    // Remove the length that comes before the closing tag.
+    let line = message[..end]
+        .windows(2)
+        .rposition(|w| w == b"\r\n")
+        .unwrap_or(end);
+    &message[..line]
+}
+
+#[test]
+fn exceptions() {
+    assert_eq!(
+        Some(3),
+        find_exception(b"\x01\x02\x03Code: 241. DB::Exception: Memory limit exceeded")
+    );
+    assert_eq!(
+        Some(9),
+        find_exception(b"Code: 1. Code: 60. DB::Exception: Unknown table")
+    );
+    assert_eq!(None, find_exception(b"Code: . DB::Exception"));
+    assert_eq!(None, find_exception(b"Code: 60. DB::Excep"));
+    assert_eq!(None, find_exception(b""));
+
+    assert_eq!(
+        Some(true),
+        starts_with_exception(b"Code: 60. DB::Exception: x")
+    );
+    assert_eq!(
+        Some(false),
+        starts_with_exception(b"\x01Code: 60. DB::Exception")
+    );
+    assert_eq!(
+        Some(false),
+        starts_with_exception(b"Code: x. DB::Exception")
+    );
+    assert_eq!(
+        Some(false),
+        starts_with_exception(b"Code: 60, DB::Exception")
+    );
+    assert_eq!(None, starts_with_exception(b"Code: 60. DB::Ex"));
+    assert_eq!(None, starts_with_exception(b"Code: 60"));
+    assert_eq!(None, starts_with_exception(b"Cod"));
+    assert_eq!(None, starts_with_exception(b""));
+
+    let body = b"\x07__exception__\r\nabcdefghijklmnop\r\nCode: 241. DB::Exception: Memory limit exceeded\r\n48 abcdefghijklmnop\r\n__exception__\r\n";
+    let i = find_tagged_exception(body, "abcdefghijklmnop").unwrap();
+    assert_eq!(1, i);
+    assert_eq!(
+        &b"Code: 241. DB::Exception: Memory limit exceeded"[..],
+        untag_exception(&body[i..], "abcdefghijklmnop")
+    );
+    assert_eq!(None, find_tagged_exception(body, "ponmlkjihgfedcba"));
+}
+
+#[test]
+fn server_errors() {
+    let e = Error::from_server_text(
+        "Code: 60. DB::Exception: Table default.t does not exist. (UNKNOWN_TABLE) (version 23.8.1.1)",
+        404,
+    )
+    .unwrap();
+    assert_eq!(Some(ErrorCode::UnknownTable), e.server_code());
+    match e {
+        Error::ServerError {
+            name,
+            message,
+            http_status,
+            ..
+        } => {
+            assert_eq!("UNKNOWN_TABLE", name);
+            assert_eq!("Table default.t does not exist.", message);
+            assert_eq!(404, http_status);
+        }
+        _ => unreachable!(),
+    }
+
+    // Older servers use a different format, with no name.
+    match Error::from_server_text(
+        "Code: 241, e.displayText() = DB::Exception: Memory limit exceeded (version 20.3.1)",
+        500,
+    ) {
+        Some(Error::ServerError {
+            code,
+            name,
+            message,
+            ..
+        }) => {
+            assert_eq!(ErrorCode::MemoryLimitExceeded, code);
+            assert_eq!("MEMORY_LIMIT_EXCEEDED", name);
+            assert_eq!("Memory limit exceeded", message);
+        }
+        e => panic!("unexpected {e:?}"),
+    }
+
+    assert!(Error::from_server_text("502 Bad Gateway", 502).is_none());
+
+    let busy = Error::from_server_text(
+        "Code: 202. DB::Exception: Too many simultaneous queries. (TOO_MANY_SIMULTANEOUS_QUERIES)",
+        500,
+    )
+    .unwrap();
+    assert!(busy.is_retryable());
+    let syntax = Error::from_server_text("Code: 62. DB::Exception: Syntax error", 400).unwrap();
+    assert!(!syntax.is_retryable());
+    assert!(!Error::RowNotFound.is_retryable());
+    let gateway = |status| Error::HttpStatus {
+        status,
+        message: String::new(),
+    };
+    assert!(gateway(504).is_retryable());
+    assert!(!gateway(404).is_retryable());
+    assert!(matches!(
+        Error::from_exception(b"Code: 1234. DB::Exception: odd (SOME_ERROR)\n"),
+        Error::ServerError {
+            code: ErrorCode::Other(1234),
+            http_status: 200,
+            ..
+        }
+    ));
+}
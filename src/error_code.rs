@@ -0,0 +1,98 @@
+//! The error codes that clickhouse uses for exceptions.
+
+macro_rules! error_codes {
+    ($($(#[$meta:meta])* $variant:ident = $code:literal $name:literal,)*) => {
+        /// The code of an exception reported by clickhouse.
+        ///
+        /// The most common codes have their own variant, and any other code is
+        /// represented by [`ErrorCode::Other`].  The full list is in
+        /// clickhouse's `src/Common/ErrorCodes.cpp`.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[non_exhaustive]
+        pub enum ErrorCode {
+            $($(#[$meta])* $variant,)*
+            /// A code without its own variant.
+            Other(u32),
+        }
+
+        impl ErrorCode {
+            /// The numeric code.
+            pub fn code(self) -> u32 {
+                match self {
+                    $(ErrorCode::$variant => $code,)*
+                    ErrorCode::Other(code) => code,
+                }
+            }
+
+            /// The name clickhouse uses for the code, such as `UNKNOWN_TABLE`,
+            /// if it has its own variant.
+            pub fn name(self) -> Option<&'static str> {
+                match self {
+                    $(ErrorCode::$variant => Some($name),)*
+                    ErrorCode::Other(_) => None,
+                }
+            }
+        }
+
+        impl From<u32> for ErrorCode {
+            fn from(code: u32) -> Self {
+                match code {
+                    $($code => ErrorCode::$variant,)*
+                    code => ErrorCode::Other(code),
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    NoSuchColumnInTable = 16 "NO_SUCH_COLUMN_IN_TABLE",
+    CannotParseInputAssertionFailed = 27 "CANNOT_PARSE_INPUT_ASSERTION_FAILED",
+    BadArguments = 36 "BAD_ARGUMENTS",
+    IllegalTypeOfArgument = 43 "ILLEGAL_TYPE_OF_ARGUMENT",
+    UnknownFunction = 46 "UNKNOWN_FUNCTION",
+    UnknownIdentifier = 47 "UNKNOWN_IDENTIFIER",
+    TypeMismatch = 53 "TYPE_MISMATCH",
+    TableAlreadyExists = 57 "TABLE_ALREADY_EXISTS",
+    UnknownTable = 60 "UNKNOWN_TABLE",
+    SyntaxError = 62 "SYNTAX_ERROR",
+    UnknownDatabase = 81 "UNKNOWN_DATABASE",
+    DatabaseAlreadyExists = 82 "DATABASE_ALREADY_EXISTS",
+    TooManyRows = 158 "TOO_MANY_ROWS",
+    TimeoutExceeded = 159 "TIMEOUT_EXCEEDED",
+    TooSlow = 160 "TOO_SLOW",
+    Readonly = 164 "READONLY",
+    TooManySimultaneousQueries = 202 "TOO_MANY_SIMULTANEOUS_QUERIES",
+    SocketTimeout = 209 "SOCKET_TIMEOUT",
+    NetworkError = 210 "NETWORK_ERROR",
+    MemoryLimitExceeded = 241 "MEMORY_LIMIT_EXCEEDED",
+    TableIsReadOnly = 242 "TABLE_IS_READ_ONLY",
+    TooManyParts = 252 "TOO_MANY_PARTS",
+    TooFewLiveReplicas = 285 "TOO_FEW_LIVE_REPLICAS",
+    TooManyBytes = 307 "TOO_MANY_BYTES",
+    UnknownStatusOfInsert = 319 "UNKNOWN_STATUS_OF_INSERT",
+    QueryWasCancelled = 394 "QUERY_WAS_CANCELLED",
+    AccessDenied = 497 "ACCESS_DENIED",
+    AuthenticationFailed = 516 "AUTHENTICATION_FAILED",
+    KeeperException = 999 "KEEPER_EXCEPTION",
+    UnknownException = 1002 "UNKNOWN_EXCEPTION",
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{name} ({})", self.code()),
+            None => write!(f, "{}", self.code()),
+        }
+    }
+}
+
+#[test]
+fn codes() {
+    assert_eq!(ErrorCode::UnknownTable, ErrorCode::from(60));
+    assert_eq!(60, ErrorCode::UnknownTable.code());
+    assert_eq!(Some("TOO_MANY_PARTS"), ErrorCode::TooManyParts.name());
+    assert_eq!(ErrorCode::Other(12345), ErrorCode::from(12345));
+    assert_eq!(None, ErrorCode::Other(12345).name());
+    assert_eq!("UNKNOWN_TABLE (60)", ErrorCode::UnknownTable.to_string());
+}
@@ -0,0 +1,150 @@
+//! Quoting of identifiers, such as table and column names, in generated SQL.
+
+use std::fmt::{Display, Formatter};
+
+use crate::Error;
+
+/// The name of a database, table or column, which is quoted when it is used
+/// in SQL.
+///
+/// An `Identifier` may contain any characters, including dots, dashes,
+/// spaces and backquotes, and may be a reserved word such as `select`.  It is
+/// written in backquotes, so it is never interpreted as anything but a name.
+/// Its [`Display`] implementation gives the quoted form.
+///
+/// # Example
+/// ```
+/// let name = streamhouse::Identifier::new("page-views.2024")?;
+/// assert_eq!("`page-views.2024`", name.to_string());
+/// # Ok::<(), streamhouse::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Identifier(String);
+
+impl Identifier {
+    /// Create an identifier, which must not be empty or contain a nul
+    /// character.
+    pub fn new(name: impl Into<String>) -> Result<Self, Error> {
+        let name = name.into();
+        if name.is_empty() || name.contains('\0') {
+            return Err(Error::InvalidIdentifier(name));
+        }
+        Ok(Identifier(name))
+    }
+
+    /// The name, without quotes.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Identifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut quoted = String::with_capacity(self.0.len() + 2);
+        write_quoted(&self.0, &mut quoted);
+        f.write_str(&quoted)
+    }
+}
+
+impl TryFrom<&str> for Identifier {
+    type Error = Error;
+    fn try_from(name: &str) -> Result<Self, Error> {
+        Identifier::new(name)
+    }
+}
+impl TryFrom<String> for Identifier {
+    type Error = Error;
+    fn try_from(name: String) -> Result<Self, Error> {
+        Identifier::new(name)
+    }
+}
+
+/// A table, optionally in a database other than the client's default one.
+///
+/// A `TableRef` may be used anywhere an [`Insert`](crate::Insert) is
+/// expected.  A plain `&str` table name may not contain a dot, so to insert
+/// into a table in another database, use a `TableRef` with a `database`.
+///
+/// # Example
+/// ```
+/// let table = streamhouse::TableRef::new("events")?.with_database("analytics")?;
+/// assert_eq!("`analytics`.`events`", table.to_string());
+/// # Ok::<(), streamhouse::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TableRef {
+    pub database: Option<Identifier>,
+    pub table: Identifier,
+}
+
+impl TableRef {
+    /// A table in the client's default database.
+    pub fn new(table: impl Into<String>) -> Result<Self, Error> {
+        Ok(TableRef {
+            database: None,
+            table: Identifier::new(table)?,
+        })
+    }
+    /// The database that the table is in.
+    pub fn with_database(self, database: impl Into<String>) -> Result<Self, Error> {
+        Ok(TableRef {
+            database: Some(Identifier::new(database)?),
+            ..self
+        })
+    }
+}
+
+impl Display for TableRef {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(database) = &self.database {
+            write!(f, "{database}.")?;
+        }
+        write!(f, "{}", self.table)
+    }
+}
+
+impl From<Identifier> for TableRef {
+    fn from(table: Identifier) -> Self {
+        TableRef {
+            database: None,
+            table,
+        }
+    }
+}
+
+/// Append `name` to `out` as a backquoted identifier, escaping any
+/// backquotes or backslashes within it.
+pub(crate) fn write_quoted(name: &str, out: &mut String) {
+    out.push('`');
+    for c in name.chars() {
+        if c == '`' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('`');
+}
+
+#[test]
+fn quoting() {
+    let quote = |name| {
+        let mut out = String::new();
+        write_quoted(name, &mut out);
+        out
+    };
+    assert_eq!("`value`", quote("value"));
+    assert_eq!("`nested.field`", quote("nested.field"));
+    assert_eq!("`select`", quote("select"));
+    assert_eq!(r"`a\`b\\c`", quote(r"a`b\c"));
+
+    let table = TableRef::new("my-table").unwrap();
+    assert_eq!("`my-table`", table.to_string());
+    let table = table.with_database("db.1").unwrap();
+    assert_eq!("`db.1`.`my-table`", table.to_string());
+    assert_eq!(
+        r"`x\`; DROP TABLE y; --`",
+        Identifier::new("x`; DROP TABLE y; --").unwrap().to_string()
+    );
+    assert!(Identifier::new("").is_err());
+    assert!(Identifier::new("a\0b").is_err());
+}
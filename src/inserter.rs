@@ -0,0 +1,213 @@
+//! Inserting rows in batches, one `INSERT` per batch.
+
+use std::time::{Duration, Instant};
+
+use crate::query::insert_header;
+use crate::{Client, Error, Insert, QuerySummary, Row};
+
+/// Inserts rows in batches, sending a separate `INSERT` for each batch.
+///
+/// Rows are buffered in memory as they are [written](Inserter::write), and
+/// are sent when the batch reaches a maximum number of rows or bytes, or
+/// when a maximum period has passed since the last batch was sent.  This
+/// suits producers that run indefinitely, for which
+/// [`Client::insert_stream`] would hold a single request open forever.
+///
+/// Call [`Inserter::end`] to send the last batch.
+///
+/// If the insert has a deduplication token from
+/// [`Insert::with_deduplication_token`], each batch is sent with that token
+/// followed by `-` and the number of the batch, counting from zero, so that
+/// the server does not discard later batches as duplicates of the first.
+///
+/// # Example
+/// ```no_run
+/// # async fn example() -> Result<(), streamhouse::Error> {
+/// # let client = streamhouse::Client::builder().with_url("http://localhost:8123").build();
+/// use std::time::Duration;
+/// let mut inserter = client
+///     .inserter::<u64>("numbers")
+///     .with_max_rows(100_000)
+///     .with_period(Duration::from_secs(10));
+/// for i in 0..1_000_000u64 {
+///     if let Some(summary) = inserter.write(&i).await? {
+///         println!("inserted {} rows", summary.written_rows);
+///     }
+/// }
+/// inserter.end().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Inserter<R> {
+    client: Client,
+    insert: Insert,
+    max_rows: u64,
+    max_bytes: u64,
+    period: Option<Duration>,
+    deduplication_token: Option<String>,
+    batches: u64,
+    header_len: usize,
+    buffer: Vec<u8>,
+    rows: u64,
+    failed: Option<Batch>,
+    last_commit: Instant,
+    _phantom: std::marker::PhantomData<fn(&R)>,
+}
+
+/// A batch that failed to send, which is kept as it was so that it may be
+/// sent again with the same deduplication token.
+struct Batch {
+    insert: Insert,
+    body: hyper::body::Bytes,
+    header_len: usize,
+    rows: u64,
+}
+
+impl Client {
+    /// Create an [`Inserter`] which inserts rows in batches.
+    ///
+    /// By default, a batch is sent for every 500,000 rows or 256MiB of data,
+    /// whichever comes first.
+    pub fn inserter<R: Row>(&self, insert: impl Into<Insert>) -> Inserter<R> {
+        let insert = insert.into();
+        let deduplication_token = insert
+            .settings
+            .get("insert_deduplication_token")
+            .filter(|_| !insert.auto_deduplication_token)
+            .map(str::to_string);
+        Inserter {
+            client: self.clone(),
+            insert,
+            max_rows: 500_000,
+            max_bytes: 256 << 20,
+            period: None,
+            deduplication_token,
+            batches: 0,
+            header_len: 0,
+            buffer: Vec::new(),
+            rows: 0,
+            failed: None,
+            last_commit: Instant::now(),
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<R: Row> Inserter<R> {
+    /// Send a batch once it has this many rows.
+    pub fn with_max_rows(self, max_rows: u64) -> Self {
+        Inserter { max_rows, ..self }
+    }
+    /// Send a batch once it has this many bytes of uncompressed RowBinary
+    /// data.
+    pub fn with_max_bytes(self, max_bytes: u64) -> Self {
+        Inserter { max_bytes, ..self }
+    }
+    /// Send a batch once this much time has passed since the last one.
+    ///
+    /// The time is only checked by [`Inserter::write`] and
+    /// [`Inserter::commit`], so a producer that may go quiet should call
+    /// `commit` periodically.
+    pub fn with_period(self, period: Duration) -> Self {
+        Inserter {
+            period: Some(period),
+            ..self
+        }
+    }
+
+    /// The number of rows waiting to be sent, including those in a batch
+    /// that failed to send.
+    pub fn pending_rows(&self) -> u64 {
+        self.rows + self.failed.as_ref().map_or(0, |batch| batch.rows)
+    }
+
+    /// Add a row to the current batch, and send the batch if it is full.
+    ///
+    /// This returns the summary of the insert if a batch was sent.  If the
+    /// row cannot be written, it is not added to the batch.
+    pub async fn write(&mut self, row: &R) -> Result<Option<QuerySummary>, Error> {
+        if self.buffer.is_empty() {
+            self.buffer = insert_header::<R>(&self.insert)?;
+            self.header_len = self.buffer.len();
+        }
+        let len = self.buffer.len();
+        if let Err(e) = row.write(&mut self.buffer) {
+            self.buffer.truncate(len);
+            return Err(e);
+        }
+        self.rows += 1;
+        self.commit().await
+    }
+
+    /// Send the current batch if it has reached any of the limits.
+    ///
+    /// This returns the summary of the insert if a batch was sent.
+    pub async fn commit(&mut self) -> Result<Option<QuerySummary>, Error> {
+        let bytes = self.buffer.len().saturating_sub(self.header_len) as u64;
+        let expired = self
+            .period
+            .is_some_and(|period| self.last_commit.elapsed() >= period);
+        if self.rows >= self.max_rows || bytes >= self.max_bytes || expired {
+            self.force_commit().await
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Send the current batch, if it has any rows.
+    ///
+    /// If sending fails, the batch is kept as it is, and rows written after
+    /// that go into a new batch.  The failed batch is sent again before the
+    /// next batch, which is only safe if the insert has a deduplication
+    /// token, such as from [`Insert::with_auto_deduplication_token`].  If
+    /// both are sent, the summary covers both of them.
+    pub async fn force_commit(&mut self) -> Result<Option<QuerySummary>, Error> {
+        self.last_commit = Instant::now();
+        let mut summary = None;
+        if let Some(batch) = self.failed.take() {
+            summary = Some(self.send(batch).await?);
+        }
+        if self.rows == 0 {
+            return Ok(summary);
+        }
+        let insert = match &self.deduplication_token {
+            Some(token) => self
+                .insert
+                .clone()
+                .with_deduplication_token(format!("{token}-{}", self.batches)),
+            None => self.insert.clone(),
+        };
+        self.batches += 1;
+        let batch = Batch {
+            insert,
+            body: std::mem::take(&mut self.buffer).into(),
+            header_len: self.header_len,
+            rows: std::mem::take(&mut self.rows),
+        };
+        let sent = self.send(batch).await?;
+        Ok(Some(match summary {
+            Some(summary) => summary.combine(&sent),
+            None => sent,
+        }))
+    }
+
+    /// Send a batch, keeping it to be sent again if this fails.
+    async fn send(&mut self, batch: Batch) -> Result<QuerySummary, Error> {
+        let result = self
+            .client
+            .send_insert::<R>(&batch.insert, batch.body.clone(), batch.header_len)
+            .await;
+        if result.is_err() {
+            self.failed = Some(batch);
+        }
+        result
+    }
+
+    /// Send any remaining rows.
+    ///
+    /// If this fails, the rows are kept, and `end` may be called again to
+    /// retry.
+    pub async fn end(&mut self) -> Result<Option<QuerySummary>, Error> {
+        self.force_commit().await
+    }
+}
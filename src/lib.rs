@@ -1,12 +1,35 @@
 extern crate self as streamhouse;
 
+mod compression;
 mod error;
 pub use error::Error;
+mod error_code;
+pub use error_code::ErrorCode;
 
+mod identifier;
+pub use identifier::{Identifier, TableRef};
+mod inserter;
+pub use inserter::Inserter;
+mod param;
 mod query;
+mod request;
+pub use request::{AsyncInsert, Insert, Query};
+mod retry;
+pub use retry::RetryPolicy;
+mod sink;
+pub use sink::InsertSink;
 mod stream;
+pub use stream::{ColumnInfo, RowStream};
+mod summary;
+pub use summary::QuerySummary;
 pub mod types;
 
+#[cfg(feature = "rustls-tls")]
+pub mod tls;
+
+mod connector;
+use connector::BoxConnector;
+
 pub(crate) mod row;
 pub(crate) use row::{Column, WriteRowBinary};
 pub use row::{Row, RowAs};
@@ -30,13 +53,17 @@ pub enum Compression {
 ///
 /// Note that cloning the `Client` is reasonably inexpensive, and internally it
 /// stores a connection pool.
+#[derive(Clone)]
 pub struct Client {
-    client: hyper::Client<hyper::client::HttpConnector>,
+    client: hyper::Client<BoxConnector>,
     url: String,
     user: Option<String>,
     password: Option<String>,
     database: Option<String>,
     compression: Compression,
+    settings: request::Settings,
+    kill_on_drop: bool,
+    retry_policy: RetryPolicy,
 }
 
 impl Client {
@@ -54,6 +81,12 @@ pub struct ClientBuilder {
     password: Option<String>,
     database: Option<String>,
     compression: Compression,
+    settings: request::Settings,
+    kill_on_drop: bool,
+    retry_policy: RetryPolicy,
+    connector: Option<BoxConnector>,
+    #[cfg(feature = "rustls-tls")]
+    tls: tls::TlsOptions,
 }
 
 impl ClientBuilder {
@@ -87,21 +120,147 @@ impl ClientBuilder {
             ..self
         }
     }
+    /// Set a clickhouse setting, such as `max_execution_time`, for every
+    /// request made by the client.
+    ///
+    /// This may be overridden for an individual request using
+    /// [`Query::with_setting`] or [`Insert::with_setting`].
+    pub fn with_setting(mut self, name: impl Into<String>, value: impl std::fmt::Display) -> Self {
+        self.settings.set(name, value);
+        self
+    }
+    /// Use clickhouse's asynchronous inserts for every insert made by the
+    /// client.
+    ///
+    /// This may be overridden for an individual insert using
+    /// [`Insert::with_async_insert`], or with the `async_insert` setting.
+    pub fn with_async_insert(mut self, async_insert: AsyncInsert) -> Self {
+        async_insert.apply(&mut self.settings);
+        self
+    }
+    /// Kill queries on the server when their [`RowStream`] is dropped before
+    /// all rows have been read.
+    ///
+    /// The `KILL QUERY` is sent in the background on the current tokio
+    /// runtime.  This may be overridden for an individual query using
+    /// [`Query::with_kill_on_drop`].
+    pub fn with_kill_on_drop(self, kill_on_drop: bool) -> Self {
+        ClientBuilder {
+            kill_on_drop,
+            ..self
+        }
+    }
+    /// Retry requests that fail with a [retryable](Error::is_retryable)
+    /// error, according to `policy`.
+    ///
+    /// See [`RetryPolicy`] for which requests are retried.
+    pub fn with_retry_policy(self, retry_policy: RetryPolicy) -> Self {
+        ClientBuilder {
+            retry_policy,
+            ..self
+        }
+    }
+    /// Connect using a custom connector, rather than directly over TCP.
+    ///
+    /// This accepts any connector that implements
+    /// [`hyper::client::connect::Connect`], such as a proxy connector or an
+    /// in-memory transport.  When a custom connector is used, any TLS options
+    /// are ignored.
+    pub fn with_connector<C>(self, connector: C) -> Self
+    where
+        C: tower_service::Service<hyper::Uri> + Clone + Send + Sync + 'static,
+        C::Response: tokio::io::AsyncRead
+            + tokio::io::AsyncWrite
+            + hyper::client::connect::Connection
+            + Unpin
+            + Send
+            + 'static,
+        C::Future: Send + 'static,
+        C::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        ClientBuilder {
+            connector: Some(BoxConnector::new(connector)),
+            ..self
+        }
+    }
+    /// Trust an additional root certificate when connecting over HTTPS.
+    ///
+    /// Certificates may be read from a PEM file using
+    /// [`tls::certificates_from_pem`].
+    #[cfg(feature = "rustls-tls")]
+    pub fn with_root_certificate(mut self, certificate: tls::Certificate) -> Self {
+        self.tls.roots.push(certificate);
+        self
+    }
+    /// Authenticate to the server with a client certificate (mTLS).
+    ///
+    /// The `chain` should begin with the client certificate itself, followed by
+    /// any intermediate certificates.
+    #[cfg(feature = "rustls-tls")]
+    pub fn with_client_certificate(
+        self,
+        chain: Vec<tls::Certificate>,
+        key: tls::PrivateKey,
+    ) -> Self {
+        ClientBuilder {
+            tls: tls::TlsOptions {
+                client_auth: Some((chain, key)),
+                ..self.tls
+            },
+            ..self
+        }
+    }
+    /// Accept any server certificate, even if it is invalid or self-signed.
+    ///
+    /// This is insecure, and should only be used for local testing.
+    #[cfg(feature = "rustls-tls")]
+    pub fn with_danger_accept_invalid_certs(self, accept_invalid_certs: bool) -> Self {
+        ClientBuilder {
+            tls: tls::TlsOptions {
+                accept_invalid_certs,
+                ..self.tls
+            },
+            ..self
+        }
+    }
+    /// Use a custom `rustls` configuration, which overrides all other TLS
+    /// options.
+    #[cfg(feature = "rustls-tls")]
+    pub fn with_tls_config(self, config: tls::ClientConfig) -> Self {
+        ClientBuilder {
+            tls: tls::TlsOptions {
+                config: Some(config),
+                ..self.tls
+            },
+            ..self
+        }
+    }
+    /// Construct the [`Client`].
+    ///
+    /// # Panics
+    ///
+    /// This panics if no url was specified, or if an invalid certificate was
+    /// given.
     pub fn build(self) -> Client {
         let url = self.url.expect("Need to specify url for Client");
-        let url = if self.compression != Compression::None {
-            format!("{url}?compress=1")
-            // format!("{url}?enable_http_compression=1")
-        } else {
-            url
-        };
+        #[cfg(feature = "rustls-tls")]
+        let connector = self
+            .connector
+            .unwrap_or_else(|| BoxConnector::new(self.tls.connector()));
+        #[cfg(not(feature = "rustls-tls"))]
+        let connector = self
+            .connector
+            .unwrap_or_else(|| BoxConnector::new(hyper::client::HttpConnector::new()));
         Client {
-            client: self.client.build_http(),
+            client: self.client.build(connector),
             url,
             user: self.user,
             password: self.password,
             database: self.database,
             compression: self.compression,
+            settings: self.settings,
+            kill_on_drop: self.kill_on_drop,
+            retry_policy: self.retry_policy,
         }
     }
 }
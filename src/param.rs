@@ -0,0 +1,541 @@
+//! Conversion of [`Row`] values into query parameters.
+//!
+//! clickhouse parses the value of a `{name:Type}` query parameter from text,
+//! so we serialize the value as RowBinary and then use its column type to
+//! convert that into the text that clickhouse expects.  This means that the
+//! parameter has exactly the value that clickhouse would have seen if the
+//! same value had been inserted.
+
+use crate::row::{single_column, Bytes};
+use crate::types::{format_decimal, ymd_from_days, I256, U256};
+use crate::{Error, Row};
+
+/// Convert a value into the text of a query parameter.
+pub(crate) fn to_text<R: Row>(value: &R) -> Result<Vec<u8>, Error> {
+    let column_type = single_column::<R>();
+    let parsed = TypeParser {
+        s: &column_type,
+        original: &column_type,
+    }
+    .parse_all()?;
+    let mut bytes = Vec::new();
+    value.write(&mut bytes)?;
+    let mut buf = Bytes { buf: &bytes };
+    let mut out = Vec::new();
+    write_text(&parsed, &mut buf, false, &mut out)?;
+    Ok(out)
+}
+
+/// Check that a parameter name is a valid identifier.
+pub(crate) fn check_name(name: &str) -> Result<(), Error> {
+    let mut chars = name.chars();
+    if chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        Ok(())
+    } else {
+        Err(Error::InvalidParams(
+            format!("invalid parameter name {name:?}").into(),
+        ))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Type {
+    UInt(usize),
+    Int(usize),
+    Float32,
+    Float64,
+    Decimal { bytes: usize, scale: u32 },
+    Bool,
+    String,
+    FixedString(usize),
+    Uuid,
+    Ipv4,
+    Ipv6,
+    Date,
+    Date32,
+    DateTime,
+    DateTime64(u32),
+    Enum8(Vec<(String, i64)>),
+    Enum16(Vec<(String, i64)>),
+    Array(Box<Type>),
+    Nullable(Box<Type>),
+    Tuple(Vec<Type>),
+    Map(Box<Type>, Box<Type>),
+}
+
+struct TypeParser<'a> {
+    s: &'a str,
+    original: &'a str,
+}
+
+impl<'a> TypeParser<'a> {
+    fn error(&self) -> Error {
+        Error::UnsupportedColumn(self.original.to_string())
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.s = self.s.trim_start();
+    }
+
+    fn eat(&mut self, c: char) -> Result<(), Error> {
+        self.skip_whitespace();
+        self.s = self.s.strip_prefix(c).ok_or_else(|| self.error())?;
+        Ok(())
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.s.chars().next()
+    }
+
+    fn word(&mut self) -> &'a str {
+        self.skip_whitespace();
+        let end = self
+            .s
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(self.s.len());
+        let (word, rest) = self.s.split_at(end);
+        self.s = rest;
+        word
+    }
+
+    fn number(&mut self) -> Result<i64, Error> {
+        self.skip_whitespace();
+        let end = self
+            .s
+            .char_indices()
+            .find(|&(i, c)| !(c.is_ascii_digit() || (i == 0 && c == '-')))
+            .map_or(self.s.len(), |(i, _)| i);
+        let (number, rest) = self.s.split_at(end);
+        self.s = rest;
+        number.parse().map_err(|_| self.error())
+    }
+
+    fn quoted_string(&mut self) -> Result<String, Error> {
+        self.eat('\'')?;
+        let mut out = String::new();
+        let mut chars = self.s.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\'' => {
+                    self.s = &self.s[i + 1..];
+                    return Ok(out);
+                }
+                '\\' => out.push(chars.next().ok_or_else(|| self.error())?.1),
+                c => out.push(c),
+            }
+        }
+        Err(self.error())
+    }
+
+    fn parse_all(mut self) -> Result<Type, Error> {
+        let t = self.parse()?;
+        if self.peek().is_some() {
+            return Err(self.error());
+        }
+        Ok(t)
+    }
+
+    fn parse(&mut self) -> Result<Type, Error> {
+        let t = match self.word() {
+            "UInt8" => Type::UInt(1),
+            "UInt16" => Type::UInt(2),
+            "UInt32" => Type::UInt(4),
+            "UInt64" => Type::UInt(8),
+            "UInt128" => Type::UInt(16),
+            "UInt256" => Type::UInt(32),
+            "Int8" => Type::Int(1),
+            "Int16" => Type::Int(2),
+            "Int32" => Type::Int(4),
+            "Int64" => Type::Int(8),
+            "Int128" => Type::Int(16),
+            "Int256" => Type::Int(32),
+            "Float32" => Type::Float32,
+            "Float64" => Type::Float64,
+            "Decimal" => {
+                self.eat('(')?;
+                let precision = self.number()?;
+                self.eat(',')?;
+                let scale = self.number()?;
+                self.eat(')')?;
+                let bytes = match precision {
+                    1..=9 => 4,
+                    10..=18 => 8,
+                    19..=38 => 16,
+                    39..=76 => 32,
+                    _ => return Err(self.error()),
+                };
+                Type::Decimal {
+                    bytes,
+                    scale: u32::try_from(scale).map_err(|_| self.error())?,
+                }
+            }
+            "Bool" => Type::Bool,
+            "String" => Type::String,
+            "UUID" => Type::Uuid,
+            "IPv4" => Type::Ipv4,
+            "IPv6" => Type::Ipv6,
+            "FixedString" => {
+                self.eat('(')?;
+                let n = self.number()?;
+                self.eat(')')?;
+                Type::FixedString(usize::try_from(n).map_err(|_| self.error())?)
+            }
+            "Date" => Type::Date,
+            "Date32" => Type::Date32,
+            "DateTime" => {
+                // The time zone does not affect the value.
+                if self.peek() == Some('(') {
+                    self.eat('(')?;
+                    self.quoted_string()?;
+                    self.eat(')')?;
+                }
+                Type::DateTime
+            }
+            "DateTime64" => {
+                self.eat('(')?;
+                let precision = self.number()?;
+                if self.peek() == Some(',') {
+                    self.eat(',')?;
+                    self.quoted_string()?;
+                }
+                self.eat(')')?;
+                match u32::try_from(precision) {
+                    Ok(precision) if precision <= 9 => Type::DateTime64(precision),
+                    _ => return Err(self.error()),
+                }
+            }
+            "Enum8" => Type::Enum8(self.enum_values()?),
+            "Enum16" => Type::Enum16(self.enum_values()?),
+            "Array" => Type::Array(Box::new(self.single_argument()?)),
+            "Nullable" => Type::Nullable(Box::new(self.single_argument()?)),
+            // LowCardinality has the same representation as its contents.
+            "LowCardinality" => self.single_argument()?,
+            "Tuple" => {
+                self.eat('(')?;
+                let mut types = vec![self.parse()?];
+                while self.peek() == Some(',') {
+                    self.eat(',')?;
+                    types.push(self.parse()?);
+                }
+                self.eat(')')?;
+                Type::Tuple(types)
+            }
+            "Map" => {
+                self.eat('(')?;
+                let k = self.parse()?;
+                self.eat(',')?;
+                let v = self.parse()?;
+                self.eat(')')?;
+                Type::Map(Box::new(k), Box::new(v))
+            }
+            _ => return Err(self.error()),
+        };
+        Ok(t)
+    }
+
+    fn single_argument(&mut self) -> Result<Type, Error> {
+        self.eat('(')?;
+        let t = self.parse()?;
+        self.eat(')')?;
+        Ok(t)
+    }
+
+    fn enum_values(&mut self) -> Result<Vec<(String, i64)>, Error> {
+        self.eat('(')?;
+        let mut values = Vec::new();
+        loop {
+            let name = self.quoted_string()?;
+            self.eat('=')?;
+            values.push((name, self.number()?));
+            if self.peek() == Some(',') {
+                self.eat(',')?;
+            } else {
+                break;
+            }
+        }
+        self.eat(')')?;
+        Ok(values)
+    }
+}
+
+/// Write a string, escaping as clickhouse expects.
+///
+/// Within arrays, tuples and maps (i.e. when `nested` is true) strings must
+/// also be quoted.
+fn write_string(s: &[u8], nested: bool, out: &mut Vec<u8>) {
+    if nested {
+        out.push(b'\'');
+    }
+    for &b in s {
+        match b {
+            b'\\' => out.extend(b"\\\\"),
+            b'\'' if nested => out.extend(b"\\'"),
+            b'\n' => out.extend(b"\\n"),
+            b'\t' => out.extend(b"\\t"),
+            b'\r' => out.extend(b"\\r"),
+            b'\0' => out.extend(b"\\0"),
+            0x08 => out.extend(b"\\b"),
+            0x0c => out.extend(b"\\f"),
+            b => out.push(b),
+        }
+    }
+    if nested {
+        out.push(b'\'');
+    }
+}
+
+fn write_display(v: impl std::fmt::Display, out: &mut Vec<u8>) {
+    out.extend(v.to_string().into_bytes());
+}
+
+fn write_text(t: &Type, buf: &mut Bytes, nested: bool, out: &mut Vec<u8>) -> Result<(), Error> {
+    match t {
+        Type::UInt(1) => write_display(buf.read::<u8>()?, out),
+        Type::UInt(2) => write_display(buf.read::<u16>()?, out),
+        Type::UInt(4) => write_display(buf.read::<u32>()?, out),
+        Type::UInt(8) => write_display(buf.read::<u64>()?, out),
+        Type::UInt(16) => write_display(buf.read::<u128>()?, out),
+        Type::UInt(_) => write_display(buf.read::<U256>()?, out),
+        Type::Int(1) => write_display(buf.read::<i8>()?, out),
+        Type::Int(2) => write_display(buf.read::<i16>()?, out),
+        Type::Int(4) => write_display(buf.read::<i32>()?, out),
+        Type::Int(8) => write_display(buf.read::<i64>()?, out),
+        Type::Int(16) => write_display(buf.read::<i128>()?, out),
+        Type::Int(_) => write_display(buf.read::<I256>()?, out),
+        Type::Float32 => write_display(buf.read::<f32>()?, out),
+        Type::Float64 => write_display(buf.read::<f64>()?, out),
+        Type::Decimal { bytes, scale } => {
+            let text = match bytes {
+                4 => format_decimal(buf.read::<i32>()?, *scale),
+                8 => format_decimal(buf.read::<i64>()?, *scale),
+                16 => format_decimal(buf.read::<i128>()?, *scale),
+                _ => format_decimal(buf.read::<I256>()?, *scale),
+            };
+            out.extend(text.into_bytes());
+        }
+        Type::Bool => write_display(buf.read::<bool>()?, out),
+        Type::String => {
+            let l = buf.read_leb128()?;
+            write_string(buf.read_bytes(l)?, nested, out);
+        }
+        Type::FixedString(n) => write_string(buf.read_bytes(*n)?, nested, out),
+        Type::Uuid => {
+            let bytes: [u8; 16] = buf.read()?;
+            let high = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+            let low = u64::from_le_bytes(bytes[8..].try_into().unwrap());
+            let hex = format!("{high:016x}{low:016x}");
+            let uuid = format!(
+                "{}-{}-{}-{}-{}",
+                &hex[..8],
+                &hex[8..12],
+                &hex[12..16],
+                &hex[16..20],
+                &hex[20..]
+            );
+            write_string(uuid.as_bytes(), nested, out);
+        }
+        Type::Ipv4 => {
+            let ip = std::net::Ipv4Addr::from(buf.read::<u32>()?);
+            write_string(ip.to_string().as_bytes(), nested, out);
+        }
+        Type::Ipv6 => {
+            let ip: std::net::Ipv6Addr = buf.read()?;
+            write_string(ip.to_string().as_bytes(), nested, out);
+        }
+        Type::Date | Type::Date32 => {
+            let days = if *t == Type::Date {
+                buf.read::<u16>()?.into()
+            } else {
+                buf.read::<i32>()?.into()
+            };
+            let (year, month, day) = ymd_from_days(days);
+            let date = format!("{year:04}-{month:02}-{day:02}");
+            write_string(date.as_bytes(), nested, out);
+        }
+        Type::DateTime => write_display(buf.read::<u32>()?, out),
+        Type::DateTime64(precision) => {
+            // clickhouse parses a number with a fractional part as a unix
+            // timestamp.
+            let ticks = buf.read::<i64>()?;
+            let per_second = 10u64.pow(*precision);
+            let (seconds, fraction) = (
+                ticks.unsigned_abs() / per_second,
+                ticks.unsigned_abs() % per_second,
+            );
+            if ticks < 0 {
+                out.push(b'-');
+            }
+            write_display(seconds, out);
+            if *precision > 0 {
+                let width = *precision as usize;
+                write_display(format_args!(".{fraction:0width$}"), out);
+            }
+        }
+        Type::Enum8(values) | Type::Enum16(values) => {
+            let v = if matches!(t, Type::Enum8(_)) {
+                buf.read::<i8>()? as i64
+            } else {
+                buf.read::<i16>()? as i64
+            };
+            let (name, _) = values
+                .iter()
+                .find(|(_, value)| *value == v)
+                .ok_or_else(|| Error::InvalidParams(format!("invalid enum value {v}").into()))?;
+            write_string(name.as_bytes(), nested, out);
+        }
+        Type::Array(t) => {
+            let l = buf.read_leb128()?;
+            out.push(b'[');
+            for i in 0..l {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_text(t, buf, true, out)?;
+            }
+            out.push(b']');
+        }
+        Type::Nullable(t) => {
+            if buf.read::<u8>()? == 1 {
+                out.extend(if nested { &b"NULL"[..] } else { &b"\\N"[..] });
+            } else {
+                write_text(t, buf, nested, out)?;
+            }
+        }
+        Type::Tuple(types) => {
+            out.push(b'(');
+            for (i, t) in types.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_text(t, buf, true, out)?;
+            }
+            out.push(b')');
+        }
+        Type::Map(k, v) => {
+            let l = buf.read_leb128()?;
+            out.push(b'{');
+            for i in 0..l {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_text(k, buf, true, out)?;
+                out.push(b':');
+                write_text(v, buf, true, out)?;
+            }
+            out.push(b'}');
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        Date, Date32, DateTime, DateTime64, Decimal, Decimal256, LowCardinality, Utc, Uuid,
+    };
+
+    fn text<R: Row>(value: R) -> String {
+        String::from_utf8(to_text(&value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!("137", text(137u8));
+        assert_eq!("-5", text(-5i64));
+        assert_eq!("1.5", text(1.5f64));
+        assert_eq!("true", text(true));
+        assert_eq!("it's a\\ttab\\\\", text("it's a\ttab\\".to_string()));
+        assert_eq!("abc", text(*b"abc"));
+        assert_eq!("hello", text(LowCardinality("hello".to_string())));
+        assert_eq!(
+            "01020304-0506-0708-090a-0b0c0d0e0f10",
+            text(Uuid::from([
+                8, 7, 6, 5, 4, 3, 2, 1, 16, 15, 14, 13, 12, 11, 10, 9
+            ]))
+        );
+        assert_eq!("::1", text(std::net::Ipv6Addr::LOCALHOST));
+        assert_eq!("2024-02-29", text(Date::from_ymd(2024, 2, 29).unwrap()));
+        assert_eq!("1900-01-01", text(Date32::MIN));
+        assert_eq!(
+            "1700000000",
+            text(DateTime::<Utc>::from_timestamp(1_700_000_000))
+        );
+        assert_eq!(
+            "1700000000.012",
+            text(DateTime64::<3, Utc>::from_ticks(1_700_000_000_012))
+        );
+        assert_eq!("-1.5", text(DateTime64::<1>::from_ticks(-15)));
+        assert_eq!(
+            "-12.3400",
+            text("-12.34".parse::<Decimal<18, 4>>().unwrap())
+        );
+        assert_eq!("-2.5", text("-2.5".parse::<Decimal256<40, 1>>().unwrap()));
+        assert_eq!(U256::MAX.to_string(), text(U256::MAX));
+        assert_eq!("-7", text(I256::from(-7i8)));
+    }
+
+    #[test]
+    fn compound() {
+        assert_eq!("\\N", text(None::<u8>));
+        assert_eq!("7", text(Some(7u8)));
+        assert_eq!(
+            "['a','it\\'s',NULL]",
+            text(vec![Some("a".to_string()), Some("it's".to_string()), None].into_boxed_slice())
+        );
+        assert_eq!("[1,2]", text(vec![1u8, 2]));
+        assert_eq!("a\\0b", text(crate::types::ByteString(b"a\0b".to_vec())));
+        assert_eq!("(1,'x')", text((1u32, "x".to_string())));
+        let map: std::collections::BTreeMap<String, u8> =
+            [("a".to_string(), 1), ("b".to_string(), 2)].into();
+        assert_eq!("{'a':1,'b':2}", text(map));
+    }
+
+    #[test]
+    fn unknown_enum_value() {
+        let t = Type::Enum16(vec![("a".to_string(), 1)]);
+        let mut out = Vec::new();
+        let bytes = 300i16.to_le_bytes();
+        let err = write_text(&t, &mut Bytes { buf: &bytes }, false, &mut out).unwrap_err();
+        assert!(err.to_string().contains("300"), "{err}");
+    }
+
+    #[test]
+    fn types() {
+        let parse = |s| TypeParser { s, original: s }.parse_all();
+        assert_eq!(
+            Type::Enum8(vec![("it's".to_string(), 1), ("b".to_string(), -2)]),
+            parse("Enum8('it\\'s' = 1, 'b' = -2)").unwrap()
+        );
+        assert_eq!(Type::DateTime, parse("DateTime('Europe/Berlin')").unwrap());
+        assert_eq!(Type::DateTime64(3), parse("DateTime64(3, 'UTC')").unwrap());
+        assert_eq!(Type::DateTime64(9), parse("DateTime64(9)").unwrap());
+        assert!(parse("DateTime64(10)").is_err());
+        assert!(parse("DateTime64(-1)").is_err());
+        assert_eq!(
+            Type::Decimal { bytes: 8, scale: 4 },
+            parse("Decimal(18, 4)").unwrap()
+        );
+        assert_eq!(
+            Type::Map(Box::new(Type::String), Box::new(Type::UInt(8))),
+            parse("Map(LowCardinality(String), UInt64)").unwrap()
+        );
+        assert!(parse("Point").is_err());
+        assert!(parse("Array(UInt8").is_err());
+        assert!(parse("UInt8 UInt8").is_err());
+    }
+
+    #[test]
+    fn names() {
+        assert!(check_name("id").is_ok());
+        assert!(check_name("_id2").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("2id").is_err());
+        assert!(check_name("id&x=1").is_err());
+    }
+}
@@ -1,134 +1,275 @@
 use std::borrow::Borrow;
-use std::pin::Pin;
 
+use crate::compression::{compress_lz4, decompress_body};
+use crate::identifier::write_quoted;
+use crate::request::{deduplication_token, generate_query_id, write_param, Settings};
 use crate::row::WriteRowBinary;
-use crate::stream::Stream;
-use crate::{Client, Compression, Error, Row};
-use futures_util::stream::try_unfold;
-use futures_util::{StreamExt, TryStreamExt};
-use hyper::header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH};
+use crate::stream::RowStream;
+use crate::summary::ProgressCallback;
+use crate::{Client, Compression, Error, Insert, Query, QuerySummary, Row};
+use futures_util::{SinkExt, StreamExt, TryStreamExt};
+use hyper::header::CONTENT_LENGTH;
 
 impl Client {
-    pub async fn query_fetch_all<R: Row>(&self, query: &str) -> Result<Vec<R>, Error> {
+    pub async fn query_fetch_all<R: Row>(&self, query: impl Into<Query>) -> Result<Vec<R>, Error> {
         self.query(query).await?.try_collect::<Vec<_>>().await
     }
 
-    pub async fn query<R: Row>(
-        &self,
-        query: &str,
-    ) -> Result<impl futures_util::Stream<Item = Result<R, Error>>, Error> {
-        let mut builder = self.request_builder();
+    /// Fetch the single row returned by a query.
+    ///
+    /// This fails with [`Error::RowNotFound`] if the query returns no rows,
+    /// and with [`Error::TooManyRows`] if it returns more than one.  The
+    /// rest of the response is not read.
+    pub async fn query_one<R: Row>(&self, query: impl Into<Query>) -> Result<R, Error> {
+        self.query_optional(query).await?.ok_or(Error::RowNotFound)
+    }
 
-        let query = format!("{query} FORMAT RowBinaryWithNamesAndTypes");
-        builder = builder.header(CONTENT_LENGTH, query.len().to_string());
-        if self.compression == Compression::Lz4 {
-            builder = builder.header(ACCEPT_ENCODING, "lz4");
-        }
-        let request = builder
-            .body(hyper::Body::from(query.to_string()))
-            .map_err(|err| Error::InvalidParams(Box::new(err)))?;
-        let response = self.client.request(request).await.map_err(Error::from)?;
-        if response.status() != hyper::StatusCode::OK {
-            return Err(Error::from_bad_response(response).await);
+    /// Fetch the row returned by a query, if there is one.
+    ///
+    /// This fails with [`Error::TooManyRows`] if the query returns more than
+    /// one row.  The rest of the response is not read.
+    pub async fn query_optional<R: Row>(
+        &self,
+        query: impl Into<Query>,
+    ) -> Result<Option<R>, Error> {
+        let mut rows = self.query::<R>(query).await?;
+        let Some(row) = rows.try_next().await? else {
+            return Ok(None);
+        };
+        if rows.try_next().await?.is_some() {
+            return Err(Error::TooManyRows);
         }
-        let compression = response.headers().get(CONTENT_ENCODING).cloned();
-        println!("headers are {:#?}", response.headers());
-        println!("compression is {compression:?}");
-        let mut body = response.into_body();
+        Ok(Some(row))
+    }
+
+    pub async fn query<R: Row>(&self, query: impl Into<Query>) -> Result<RowStream<R>, Error> {
+        let query = query.into();
+        let query_id = query.query_id.clone().unwrap_or_else(generate_query_id);
+        let sql =
+            hyper::body::Bytes::from(format!("{} FORMAT RowBinaryWithNamesAndTypes", query.sql));
+        let progress = query
+            .progress
+            .as_ref()
+            .and_then(|progress| progress.watch(self, &query_id));
+        // Reading rows is idempotent, so may always be retried.
+        let response = self
+            .send(true, || {
+                self.request_builder(&query.settings, &query.params, &query_id, false)
+                    .header(CONTENT_LENGTH, sql.len().to_string())
+                    .body(hyper::Body::from(sql.clone()))
+            })
+            .await?;
+        let (parts, body) = response.into_parts();
+        let kill_on_drop = if query.kill_on_drop.unwrap_or(self.kill_on_drop) {
+            Some(self.clone())
+        } else {
+            None
+        };
         if self.compression == Compression::Lz4 {
-            let mut decoder = lz4_flex::frame::Decoder::new();
-            while let Some(bytes) = body.next().await {
-                decoder.push(&bytes?);
-            }
-            let mut out = Vec::new();
-            loop {
-                println!("Reading a block");
-                match decoder.next_block() {
-                    Ok(chunk) => out.extend(chunk),
-                    Err(e) => panic!("Error decoding: {e:#}"),
-                }
-            }
-            Ok(Stream::new(hyper::Body::from(out)).await?.into_stream())
+            RowStream::new(
+                decompress_body(body),
+                &parts.headers,
+                query_id,
+                kill_on_drop,
+                progress,
+            )
+            .await
         } else {
-            Ok(Stream::new(body).await?.into_stream())
+            RowStream::new(
+                body.map_err(Error::from),
+                &parts.headers,
+                query_id,
+                kill_on_drop,
+                progress,
+            )
+            .await
         }
     }
 
-    pub async fn execute(&self, query: &str) -> Result<(), Error> {
-        let mut builder = self.request_builder();
-        builder = builder.header(CONTENT_LENGTH, query.len().to_string());
-        let request = builder
-            .body(hyper::Body::from(query.to_string()))
-            .map_err(|err| Error::InvalidParams(Box::new(err)))?;
-        let response = self.client.request(request).await.map_err(Error::from)?;
-        if response.status() != hyper::StatusCode::OK {
-            return Err(Error::from_bad_response(response).await);
-        }
-        Ok(())
+    /// Execute a query that returns no rows.
+    ///
+    /// This returns a summary of the work done by the server.
+    pub async fn execute(&self, query: impl Into<Query>) -> Result<QuerySummary, Error> {
+        let query = query.into();
+        let query_id = query.query_id.unwrap_or_else(generate_query_id);
+        let _progress = query
+            .progress
+            .as_ref()
+            .and_then(|progress| progress.watch(self, &query_id));
+        let response = self
+            .send(false, || {
+                self.request_builder(&query.settings, &query.params, &query_id, false)
+                    .header(CONTENT_LENGTH, query.sql.len().to_string())
+                    .body(hyper::Body::from(query.sql.clone()))
+            })
+            .await?;
+        finish(response, query.progress.as_ref()).await
     }
 
-    pub async fn insert<R, I>(&self, table: &str, rows: I) -> Result<(), Error>
+    /// Insert rows into a table.
+    ///
+    /// This returns a summary of the work done by the server, including the
+    /// number of rows written.
+    pub async fn insert<R, I>(
+        &self,
+        insert: impl Into<Insert>,
+        rows: I,
+    ) -> Result<QuerySummary, Error>
     where
         R: Row,
         I: IntoIterator,
         I::Item: Borrow<R>,
     {
-        let builder = self.request_builder();
-        let mut body_bytes =
-            format!("INSERT INTO {table} FORMAT RowBinaryWithNamesAndTypes\n").into_bytes();
-        let columns = R::columns("");
-        body_bytes.write_leb128(columns.len() as u64)?;
-        for n in columns.iter().map(|c| c.name) {
-            if n.is_empty() {
-                return Err(Error::MissingColumnName {
-                    row: columns.into_iter().map(|c| c.name).collect(),
-                });
-            }
-            n.to_string().write(&mut body_bytes)?;
-        }
-        for t in columns.iter().map(|c| &c.column_type) {
-            t.write(&mut body_bytes)?;
-        }
+        let insert = insert.into();
+        let mut body_bytes = insert_header::<R>(&insert)?;
+        let header_len = body_bytes.len();
         for r in rows {
             r.borrow().write(&mut body_bytes)?;
         }
+        self.send_insert::<R>(&insert, body_bytes.into(), header_len)
+            .await
+    }
+
+    /// Send an insert of rows of type `R` whose body is already in memory,
+    /// starting with the header from [`insert_header`], which is
+    /// `header_len` bytes long.
+    pub(crate) async fn send_insert<R: Row>(
+        &self,
+        insert: &Insert,
+        mut body_bytes: hyper::body::Bytes,
+        header_len: usize,
+    ) -> Result<QuerySummary, Error> {
+        let query_id = insert.query_id.clone().unwrap_or_else(generate_query_id);
+        let mut settings = insert.settings.clone();
+        if insert.auto_deduplication_token {
+            settings.set(
+                "insert_deduplication_token",
+                deduplication_token(
+                    insert.table_ref()?,
+                    &R::columns(""),
+                    &body_bytes[header_len..],
+                ),
+            );
+        }
+        if self.compression == Compression::Lz4 {
+            body_bytes = compress_lz4(&body_bytes).into();
+        }
 
-        let request = builder
-            .body(hyper::Body::from(body_bytes))
-            .map_err(|err| Error::InvalidParams(Box::new(err)))?;
-        let response = self.client.request(request).await.map_err(Error::from)?;
-        if response.status() != hyper::StatusCode::OK {
-            return Err(Error::from_bad_response(response).await);
+        // An insert may only be retried if the server will deduplicate it,
+        // which it cannot do for an async insert that was not waited for.
+        let setting = |name| settings.get(name).or(self.settings.get(name));
+        let fire_and_forget = setting("wait_for_async_insert") == Some("0");
+        let retry = settings.contains("insert_deduplication_token") && !fire_and_forget;
+        // The server ignores the token of an async insert unless it is told
+        // to deduplicate async inserts.
+        if retry
+            && setting("async_insert") == Some("1")
+            && setting("async_insert_deduplicate").is_none()
+        {
+            settings.set("async_insert_deduplicate", 1);
         }
-        Ok(())
+        let _progress = insert
+            .progress
+            .as_ref()
+            .and_then(|progress| progress.watch(self, &query_id));
+        let response = self
+            .send(retry, || {
+                self.request_builder(&settings, &[], &query_id, true)
+                    .body(hyper::Body::from(body_bytes.clone()))
+            })
+            .await?;
+        finish(response, insert.progress.as_ref()).await
     }
 
     /// Insert a stream of rows into a table.
     ///
     /// This method is preferred over [`Client::insert`] in cases where you have
     /// so many rows that you do not want to store them all in memory.
+    ///
+    /// If `rows` yields an error, the request is aborted and that error is
+    /// returned.  The server then discards the block of rows that it was
+    /// building, but an aborted insert is **not** all or nothing: the server
+    /// commits each block of `max_insert_block_size` rows (about a million
+    /// by default) as soon as it has read it, and those rows stay in the
+    /// table.  An insert that must be atomic should use [`Client::insert`],
+    /// or set `max_insert_block_size` with [`Insert::with_setting`] to more
+    /// than the number of rows it will send.
     pub async fn insert_stream<R: Row + Send + 'static>(
         &self,
-        table: &str,
+        insert: impl Into<Insert>,
         rows: impl futures_util::Stream<Item = Result<R, Error>> + Send + 'static,
-    ) -> Result<(), Error> {
-        let rows: Pin<Box<dyn futures_util::Stream<Item = Result<R, Error>> + Send>> =
-            Box::pin(rows);
-        let builder = self.request_builder();
-        let request = builder
-            .body(row_stream_to_body(table, rows)?)
-            .map_err(|err| Error::InvalidParams(Box::new(err)))?;
-        let response = self.client.request(request).await.map_err(Error::from)?;
-        if response.status() != hyper::StatusCode::OK {
-            return Err(Error::from_bad_response(response).await);
+    ) -> Result<QuerySummary, Error> {
+        let mut sink = self.insert_sink::<R>(insert)?;
+        let mut rows = std::pin::pin!(rows);
+        while let Some(row) = rows.next().await {
+            // Returning early drops the sink, which aborts the request.
+            sink.feed(row?).await?;
+        }
+        sink.close().await?;
+        Ok(*sink.summary().expect("a closed insert has a summary"))
+    }
+
+    /// Send a request, and return the response if it succeeded.
+    ///
+    /// If `retry` is true, the request is made again by calling
+    /// `make_request` when it fails with a retryable error, according to the
+    /// client's [`RetryPolicy`](crate::RetryPolicy).
+    async fn send(
+        &self,
+        retry: bool,
+        make_request: impl Fn() -> Result<hyper::Request<hyper::Body>, hyper::http::Error>,
+    ) -> Result<hyper::Response<hyper::Body>, Error> {
+        let mut attempt = 1;
+        loop {
+            let request = make_request().map_err(|err| Error::InvalidParams(Box::new(err)))?;
+            let error = match self.client.request(request).await {
+                Ok(response) if !is_error(&response) => return Ok(response),
+                Ok(response) => Error::from_bad_response(response).await,
+                Err(err) => Error::from(err),
+            };
+            if !retry || attempt >= self.retry_policy.max_attempts() || !error.is_retryable() {
+                return Err(error);
+            }
+            tokio::time::sleep(self.retry_policy.backoff(attempt)).await;
+            attempt += 1;
         }
-        Ok(())
     }
 
-    fn request_builder(&self) -> hyper::http::request::Builder {
+    /// Create a request builder with the given `settings` in addition to the
+    /// client's default settings, and the given query parameters and
+    /// `query_id`.
+    ///
+    /// If `compressed_body` is true, the request body will be compressed when
+    /// the client uses compression.
+    pub(crate) fn request_builder(
+        &self,
+        settings: &Settings,
+        query_params: &[(String, Vec<u8>)],
+        query_id: &str,
+        compressed_body: bool,
+    ) -> hyper::http::request::Builder {
+        let mut params = String::new();
+        if self.compression != Compression::None {
+            write_param("compress", "1", &mut params);
+            if compressed_body {
+                write_param("decompress", "1", &mut params);
+            }
+        }
+        self.settings.write_query_string(settings, &mut params);
+        settings.write_query_string(&Settings::default(), &mut params);
+        for (name, value) in query_params {
+            write_param(&format!("param_{name}"), value, &mut params);
+        }
+        write_param("query_id", query_id, &mut params);
+        let uri = if self.url.contains('?') {
+            format!("{}&{params}", self.url)
+        } else {
+            format!("{}?{params}", self.url)
+        };
+
         let mut builder = hyper::Request::builder()
             .method(hyper::Method::POST)
-            .uri(self.url.as_str());
+            .uri(uri);
 
         if let Some(database) = &self.database {
             builder = builder.header("X-ClickHouse-Database", database);
@@ -143,67 +284,61 @@ impl Client {
     }
 }
 
-fn row_stream_to_body<R: Row + 'static + Send>(
-    table: &str,
-    rows: Pin<Box<dyn futures_util::Stream<Item = Result<R, Error>> + Send>>,
-) -> Result<hyper::Body, Error> {
-    let s: Box<
-        (dyn futures_util::Stream<
-            Item = Result<hyper::body::Bytes, Box<(dyn std::error::Error + Send + Sync + 'static)>>,
-        > + Send
-             + 'static),
-    > = Box::new(try_unfold(
-        RowReader::new(table, rows)?,
-        RowReader::next_and_self,
-    ));
-    Ok(hyper::Body::from(s))
+/// Returns true if the server reports that the query failed.
+///
+/// clickhouse may send an exception with a 200 status, in which case it sets
+/// the `X-ClickHouse-Exception-Code` header.
+pub(crate) fn is_error(response: &hyper::Response<hyper::Body>) -> bool {
+    response.status() != hyper::StatusCode::OK
+        || response
+            .headers()
+            .contains_key("X-ClickHouse-Exception-Code")
 }
 
-struct RowReader<R> {
-    rows: Pin<Box<dyn futures_util::Stream<Item = Vec<Result<R, Error>>> + Send>>,
-    buffer: Vec<u8>,
+/// Check the status of a response to a request that returns no rows, and
+/// read its summary.
+pub(crate) async fn finish(
+    response: hyper::Response<hyper::Body>,
+    progress: Option<&ProgressCallback>,
+) -> Result<QuerySummary, Error> {
+    if is_error(&response) {
+        return Err(Error::from_bad_response(response).await);
+    }
+    let summary = QuerySummary::from_headers(response.headers());
+    if let (Some(progress), Some(summary)) = (progress, &summary) {
+        // The summary is the final progress of the query.
+        progress.report(summary);
+    }
+    Ok(summary.unwrap_or_default())
 }
 
-const MAX_ROWS: usize = 10_000;
-
-impl<R: Row + 'static> RowReader<R> {
-    fn new(
-        table: &str,
-        rows: Pin<Box<dyn futures_util::Stream<Item = Result<R, Error>> + Send>>,
-    ) -> Result<Self, Error> {
-        let mut buffer =
-            format!("INSERT INTO {table} FORMAT RowBinaryWithNamesAndTypes\n").into_bytes();
-        let columns = R::columns("");
-        buffer.write_leb128(columns.len() as u64)?;
-        for n in columns.iter().map(|c| c.name) {
-            if n.is_empty() {
-                return Err(Error::MissingColumnName {
-                    row: columns.into_iter().map(|c| c.name).collect(),
-                });
-            }
-            n.to_string().write(&mut buffer)?;
-        }
-        for t in columns.into_iter().map(|c| c.column_type) {
-            t.write(&mut buffer)?;
-        }
-        Ok(Self {
-            rows: Box::pin(rows.ready_chunks(MAX_ROWS)),
-            buffer,
-        })
-    }
-    async fn next_and_self(
-        mut self,
-    ) -> Result<Option<(hyper::body::Bytes, Self)>, Box<dyn std::error::Error + Send + Sync>> {
-        if let Some(chunk) = self.rows.next().await {
-            for row in chunk {
-                row?.write(&mut self.buffer)?;
-            }
-        }
-        if self.buffer.is_empty() {
-            Ok(None)
-        } else {
-            let bytes = std::mem::take(&mut self.buffer);
-            Ok(Some((hyper::body::Bytes::from(bytes), self)))
+/// The start of the body of an `insert`, which is followed by the rows in
+/// RowBinary format.
+pub(crate) fn insert_header<R: Row>(insert: &Insert) -> Result<Vec<u8>, Error> {
+    let table = insert.table_ref()?;
+    let columns = R::columns("");
+    if columns.iter().any(|c| c.name.is_empty()) {
+        return Err(Error::MissingColumnName {
+            row: columns.into_iter().map(|c| c.name).collect(),
+        });
+    }
+    // Listing the columns lets the server fill in any others with their
+    // defaults.
+    let mut sql = format!("INSERT INTO {table} (");
+    for (i, c) in columns.iter().enumerate() {
+        if i > 0 {
+            sql.push_str(", ");
         }
+        write_quoted(c.name, &mut sql);
+    }
+    sql.push_str(") FORMAT RowBinaryWithNamesAndTypes\n");
+    let mut buffer = sql.into_bytes();
+    buffer.write_leb128(columns.len() as u64)?;
+    for n in columns.iter().map(|c| c.name) {
+        n.to_string().write(&mut buffer)?;
+    }
+    for t in columns.into_iter().map(|c| c.column_type) {
+        t.write(&mut buffer)?;
     }
+    Ok(buffer)
 }
@@ -0,0 +1,520 @@
+//! Per-request options, such as clickhouse settings.
+
+use std::fmt::Display;
+use std::time::Duration;
+
+use crate::summary::ProgressCallback;
+use crate::{Column, Error, Identifier, QuerySummary, Row, TableRef};
+
+/// A query, along with any settings and parameters that apply to it.
+///
+/// Anywhere a `Query` is expected, a plain `&str` may be used instead.
+///
+/// # Example
+/// ```
+/// let query = streamhouse::Query::new("SELECT count() FROM big_table")
+///     .with_setting("max_execution_time", 10)
+///     .with_setting("max_memory_usage", 1_000_000_000);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub(crate) sql: String,
+    pub(crate) settings: Settings,
+    pub(crate) params: Vec<(String, Vec<u8>)>,
+    pub(crate) query_id: Option<String>,
+    pub(crate) kill_on_drop: Option<bool>,
+    pub(crate) progress: Option<ProgressCallback>,
+}
+
+impl Query {
+    pub fn new(sql: impl Into<String>) -> Self {
+        Query {
+            sql: sql.into(),
+            settings: Settings::default(),
+            params: Vec::new(),
+            query_id: None,
+            kill_on_drop: None,
+            progress: None,
+        }
+    }
+    /// Bind a value to the `{name:Type}` placeholder in the query.
+    ///
+    /// The value is sent separately from the query, so there is no risk of SQL
+    /// injection.  Any [`Row`] type may be bound, including arrays, maps,
+    /// tuples and `Option`.
+    ///
+    /// # Example
+    /// ```
+    /// # fn main() -> Result<(), streamhouse::Error> {
+    /// let name = "Robert'); DROP TABLE students;--".to_string();
+    /// let query = streamhouse::Query::new(
+    ///     "SELECT age FROM students WHERE name = {name:String} AND age > {age:UInt8}",
+    /// )
+    /// .bind("name", &name)?
+    /// .bind("age", &12u8)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bind<R: Row>(mut self, name: &str, value: &R) -> Result<Self, Error> {
+        crate::param::check_name(name)?;
+        let value = crate::param::to_text(value)?;
+        self.params.retain(|(n, _)| n != name);
+        self.params.push((name.to_string(), value));
+        Ok(self)
+    }
+    /// Set a clickhouse setting for this query.
+    ///
+    /// This overrides any default set with
+    /// [`ClientBuilder::with_setting`](crate::ClientBuilder::with_setting).
+    pub fn with_setting(mut self, name: impl Into<String>, value: impl Display) -> Self {
+        self.settings.set(name, value);
+        self
+    }
+    /// Set the `query_id` for this query.
+    ///
+    /// If no `query_id` is given, a random one is generated.
+    pub fn with_query_id(mut self, query_id: impl Into<String>) -> Self {
+        self.query_id = Some(query_id.into());
+        self
+    }
+    /// Kill the query on the server if its [`RowStream`](crate::RowStream) is
+    /// dropped before all rows have been read.
+    ///
+    /// This overrides the default set with
+    /// [`ClientBuilder::with_kill_on_drop`](crate::ClientBuilder::with_kill_on_drop).
+    pub fn with_kill_on_drop(mut self, kill_on_drop: bool) -> Self {
+        self.kill_on_drop = Some(kill_on_drop);
+        self
+    }
+    /// Call `f` with the progress of the query while it runs.
+    ///
+    /// The progress is read from `system.processes` about once a second
+    /// while the query runs, and [`Client::execute`](crate::Client::execute)
+    /// also reports the final summary once the query is done.  `f` is called
+    /// from a background task.  Note that `system.processes` only lists the
+    /// queries running on the server that the client is connected to.
+    pub fn with_progress(mut self, f: impl Fn(&QuerySummary) + Send + Sync + 'static) -> Self {
+        self.progress = Some(ProgressCallback::new(f));
+        self
+    }
+}
+
+impl From<&str> for Query {
+    fn from(sql: &str) -> Self {
+        Query::new(sql)
+    }
+}
+impl From<&String> for Query {
+    fn from(sql: &String) -> Self {
+        Query::new(sql.as_str())
+    }
+}
+impl From<String> for Query {
+    fn from(sql: String) -> Self {
+        Query::new(sql)
+    }
+}
+
+/// The table to insert into, along with any settings that apply to the insert.
+///
+/// Anywhere an `Insert` is expected, a plain `&str` table name, an
+/// [`Identifier`] or a [`TableRef`] may be used instead.  The table name is
+/// quoted in the generated SQL, so it may contain any characters except a
+/// dot; since `"db.events"` could mean either a table in the `db` database or
+/// a table with a dot in its name, such a name is rejected with
+/// [`Error::DottedTableName`](crate::Error::DottedTableName).  A table in
+/// another database needs a [`TableRef`], and a table with a dot in its name
+/// an [`Identifier`].
+///
+/// # Example
+/// ```
+/// let insert = streamhouse::Insert::new("events").with_setting("insert_quorum", 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Insert {
+    /// The table, or the name that could not be made into one.
+    pub(crate) table: Result<TableRef, String>,
+    pub(crate) settings: Settings,
+    pub(crate) query_id: Option<String>,
+    pub(crate) progress: Option<ProgressCallback>,
+    pub(crate) auto_deduplication_token: bool,
+}
+
+impl Insert {
+    /// An insert into a table in the client's default database.
+    ///
+    /// The name is checked when the insert is made, and must not contain a
+    /// dot.
+    pub fn new(table: impl Into<String>) -> Self {
+        let table = table.into();
+        Insert::with_table(if table.contains('.') {
+            Err(table)
+        } else {
+            TableRef::new(table.clone()).map_err(|_| table)
+        })
+    }
+    fn with_table(table: Result<TableRef, String>) -> Self {
+        Insert {
+            table,
+            settings: Settings::default(),
+            query_id: None,
+            progress: None,
+            auto_deduplication_token: false,
+        }
+    }
+    /// The table to insert into.
+    pub(crate) fn table_ref(&self) -> Result<&TableRef, Error> {
+        self.table.as_ref().map_err(|name| {
+            if name.contains('.') {
+                Error::DottedTableName(name.clone())
+            } else {
+                Error::InvalidIdentifier(name.clone())
+            }
+        })
+    }
+    /// Set a clickhouse setting for this insert.
+    ///
+    /// This overrides any default set with
+    /// [`ClientBuilder::with_setting`](crate::ClientBuilder::with_setting).
+    pub fn with_setting(mut self, name: impl Into<String>, value: impl Display) -> Self {
+        self.settings.set(name, value);
+        self
+    }
+    /// Set the `query_id` for this insert.
+    ///
+    /// If no `query_id` is given, a random one is generated.
+    pub fn with_query_id(mut self, query_id: impl Into<String>) -> Self {
+        self.query_id = Some(query_id.into());
+        self
+    }
+    /// Call `f` with the progress of the insert while it runs.
+    ///
+    /// The progress is read from `system.processes` about once a second,
+    /// and once more from the summary when the insert is done.  As with
+    /// [`Query::with_progress`], `f` is called from a background task.
+    pub fn with_progress(mut self, f: impl Fn(&QuerySummary) + Send + Sync + 'static) -> Self {
+        self.progress = Some(ProgressCallback::new(f));
+        self
+    }
+    /// Use clickhouse's asynchronous inserts for this insert.
+    ///
+    /// This overrides any default set with
+    /// [`ClientBuilder::with_async_insert`](crate::ClientBuilder::with_async_insert).
+    pub fn with_async_insert(mut self, async_insert: AsyncInsert) -> Self {
+        async_insert.apply(&mut self.settings);
+        self
+    }
+    /// Send an `insert_deduplication_token`, so that if the same insert is
+    /// repeated the server only stores its rows once.
+    ///
+    /// This makes it safe to retry the insert, and a [`RetryPolicy`] does so
+    /// automatically.  Tables that are not replicated also need the
+    /// `non_replicated_deduplication_window` setting for deduplication to
+    /// happen.  An [`Inserter`](crate::Inserter) adds the number of each
+    /// batch to the token.
+    ///
+    /// [`RetryPolicy`]: crate::RetryPolicy
+    pub fn with_deduplication_token(mut self, token: impl Into<String>) -> Self {
+        self.auto_deduplication_token = false;
+        self.settings
+            .set("insert_deduplication_token", token.into());
+        self
+    }
+    /// Send an `insert_deduplication_token` that is a hash of the inserted
+    /// data, so that inserting the same rows again has no effect.
+    ///
+    /// The hash covers only the table, the names and types of the columns,
+    /// and the rows as encoded in RowBinary, so the same rows in the same
+    /// order give the same token, even in another process.  Rows whose encoding is not deterministic, such as
+    /// those with a `HashMap` field, may get a different token each time they
+    /// are written, and a future change to how a type is encoded would also
+    /// change the token.  This is only supported by
+    /// [`Client::insert`](crate::Client::insert), since
+    /// [`Client::insert_stream`](crate::Client::insert_stream) does not know
+    /// the data in advance.
+    pub fn with_auto_deduplication_token(mut self) -> Self {
+        self.auto_deduplication_token = true;
+        self
+    }
+}
+
+/// Options for clickhouse's asynchronous inserts.
+///
+/// With asynchronous inserts, the server collects the data from many small
+/// inserts in a buffer, and writes it to the table in one go when the buffer
+/// is full or has been waiting for the busy timeout.  This is much more
+/// efficient than writing each small insert as its own part.
+///
+/// By default, an insert still waits until its data has been written to the
+/// table, so a successful result means the data is stored and any error is
+/// reported.  With [`AsyncInsert::fire_and_forget`], an insert returns as soon
+/// as the data is in the buffer: a successful result then only means that
+/// the data was received, errors while writing it are not reported, and the
+/// [`QuerySummary`] does not count the rows written.  Such inserts must not be
+/// retried, since they cannot be deduplicated.
+///
+/// An asynchronous insert that waits and has a deduplication token is sent
+/// with the `async_insert_deduplicate` setting, without which the server
+/// would ignore the token, unless that setting is given explicitly.  The
+/// server only deduplicates asynchronous inserts into replicated tables.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// let insert = streamhouse::Insert::new("events").with_async_insert(
+///     streamhouse::AsyncInsert::new().with_busy_timeout(Duration::from_millis(200)),
+/// );
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AsyncInsert {
+    fire_and_forget: bool,
+    busy_timeout: Option<Duration>,
+    max_data_size: Option<u64>,
+}
+
+impl AsyncInsert {
+    /// Asynchronous inserts that wait for their data to be written.
+    pub fn new() -> Self {
+        AsyncInsert::default()
+    }
+    /// Asynchronous inserts that return as soon as the server has received
+    /// their data.
+    pub fn fire_and_forget() -> Self {
+        AsyncInsert {
+            fire_and_forget: true,
+            ..AsyncInsert::default()
+        }
+    }
+    /// The longest time the server waits before writing buffered data, which
+    /// is the `async_insert_busy_timeout_ms` setting.
+    pub fn with_busy_timeout(self, busy_timeout: Duration) -> Self {
+        AsyncInsert {
+            busy_timeout: Some(busy_timeout),
+            ..self
+        }
+    }
+    /// The number of bytes the server buffers before writing them, which is
+    /// the `async_insert_max_data_size` setting.
+    pub fn with_max_data_size(self, max_data_size: u64) -> Self {
+        AsyncInsert {
+            max_data_size: Some(max_data_size),
+            ..self
+        }
+    }
+
+    pub(crate) fn apply(&self, settings: &mut Settings) {
+        settings.set("async_insert", 1);
+        settings.set("wait_for_async_insert", u8::from(!self.fire_and_forget));
+        if let Some(timeout) = self.busy_timeout {
+            settings.set("async_insert_busy_timeout_ms", timeout.as_millis());
+        }
+        if let Some(size) = self.max_data_size {
+            settings.set("async_insert_max_data_size", size);
+        }
+    }
+}
+
+/// A token for deduplicating an insert of the given uncompressed RowBinary
+/// rows into some columns of a table.
+///
+/// Only the quoted table name, the names and types of the columns and the
+/// rows are hashed, not the `INSERT` query itself, so that changes to how the
+/// query is written do not change the token.
+pub(crate) fn deduplication_token(table: &TableRef, columns: &[Column], rows: &[u8]) -> String {
+    let mut described = Vec::new();
+    for c in columns {
+        described.extend(c.name.as_bytes());
+        described.push(0);
+        described.extend(c.column_type.as_bytes());
+        described.push(0);
+    }
+    let mut hashes = Vec::with_capacity(48);
+    for part in [table.to_string().as_bytes(), &described, rows] {
+        hashes.extend(cityhash_rs::cityhash_102_128(part).to_le_bytes());
+    }
+    format!("{:032x}", cityhash_rs::cityhash_102_128(&hashes))
+}
+
+impl From<&str> for Insert {
+    fn from(table: &str) -> Self {
+        Insert::new(table)
+    }
+}
+impl From<&String> for Insert {
+    fn from(table: &String) -> Self {
+        Insert::new(table.as_str())
+    }
+}
+impl From<String> for Insert {
+    fn from(table: String) -> Self {
+        Insert::new(table)
+    }
+}
+impl From<TableRef> for Insert {
+    fn from(table: TableRef) -> Self {
+        Insert::with_table(Ok(table))
+    }
+}
+impl From<Identifier> for Insert {
+    fn from(table: Identifier) -> Self {
+        Insert::with_table(Ok(table.into()))
+    }
+}
+
+/// A set of clickhouse settings, which are sent as URL query parameters.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Settings(Vec<(String, String)>);
+
+impl Settings {
+    pub(crate) fn set(&mut self, name: impl Into<String>, value: impl Display) {
+        let name = name.into();
+        let value = value.to_string();
+        if let Some(v) = self.0.iter_mut().find(|(n, _)| *n == name) {
+            v.1 = value;
+        } else {
+            self.0.push((name, value));
+        }
+    }
+
+    pub(crate) fn contains(&self, name: &str) -> bool {
+        self.0.iter().any(|(n, _)| n == name)
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Append these settings to a query string, skipping any that are
+    /// overridden in `overrides`.
+    pub(crate) fn write_query_string(&self, overrides: &Settings, out: &mut String) {
+        for (name, value) in self.0.iter().filter(|(n, _)| !overrides.contains(n)) {
+            write_param(name, value, out);
+        }
+    }
+}
+
+/// Generate a random number, which is good enough for ids and jitter but not
+/// for anything that needs to be unpredictable.
+pub(crate) fn random_u64() -> u64 {
+    use std::hash::{BuildHasher, Hash, Hasher};
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    COUNTER.fetch_add(1, Ordering::Relaxed).hash(&mut hasher);
+    std::time::SystemTime::now().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Generate a random `query_id`, formatted as a UUID.
+pub(crate) fn generate_query_id() -> String {
+    let words = [random_u64(), random_u64()];
+    let id = (u128::from(words[0]) << 64) | u128::from(words[1]);
+    // Mark it as a version 4 (random) UUID.
+    let id = (id & !(0xf << 76) & !(0x3 << 62)) | (0x4 << 76) | (0x2 << 62);
+    let hex = format!("{id:032x}");
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Append a URL-encoded `name=value` pair to a query string.
+pub(crate) fn write_param(name: &str, value: impl AsRef<[u8]>, out: &mut String) {
+    if !out.is_empty() {
+        out.push('&');
+    }
+    url_encode(name.as_bytes(), out);
+    out.push('=');
+    url_encode(value.as_ref(), out);
+}
+
+fn url_encode(s: &[u8], out: &mut String) {
+    for &b in s {
+        if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+}
+
+#[test]
+fn query_string() {
+    let mut defaults = Settings::default();
+    defaults.set("max_execution_time", 5);
+    defaults.set("join_use_nulls", 1);
+    let mut overrides = Settings::default();
+    overrides.set("max_execution_time", 10);
+    overrides.set("log_comment", "a & b=c");
+
+    let mut out = String::new();
+    defaults.write_query_string(&overrides, &mut out);
+    overrides.write_query_string(&Settings::default(), &mut out);
+    assert_eq!(
+        "join_use_nulls=1&max_execution_time=10&log_comment=a%20%26%20b%3Dc",
+        out
+    );
+}
+
+#[test]
+fn deduplication_tokens() {
+    let t = TableRef::new("t").unwrap();
+    let x = [Column::new("x", "UInt32")];
+    let token = deduplication_token(&t, &x, b"\x01\x00\x00\x00");
+    assert_eq!(32, token.len());
+    assert_eq!(token, deduplication_token(&t, &x, b"\x01\x00\x00\x00"));
+    assert_ne!(token, deduplication_token(&t, &x, b"\x02\x00\x00\x00"));
+    let u = TableRef::new("u").unwrap();
+    assert_ne!(token, deduplication_token(&u, &x, b"\x01\x00\x00\x00"));
+    let db_t = t.clone().with_database("db").unwrap();
+    assert_ne!(token, deduplication_token(&db_t, &x, b"\x01\x00\x00\x00"));
+
+    // The same bytes in other columns are a different insert.
+    let y = [Column::new("y", "UInt32")];
+    assert_ne!(token, deduplication_token(&t, &y, b"\x01\x00\x00\x00"));
+    let x_int = [Column::new("x", "Int32")];
+    assert_ne!(token, deduplication_token(&t, &x_int, b"\x01\x00\x00\x00"));
+
+    // The token for a fixed row must never change, or retries after an
+    // upgrade would insert duplicates.
+    #[derive(Row)]
+    struct Event {
+        id: u32,
+        name: String,
+    }
+    let mut rows = Vec::new();
+    Event {
+        id: 1,
+        name: "hello".to_string(),
+    }
+    .write(&mut rows)
+    .unwrap();
+    assert_eq!(
+        "295011294243806ce3dbda781f603fbc",
+        deduplication_token(
+            &TableRef::new("events").unwrap(),
+            &Event::columns(""),
+            &rows
+        )
+    );
+}
+
+#[test]
+fn query_ids() {
+    let a = generate_query_id();
+    let b = generate_query_id();
+    assert_ne!(a, b);
+    assert_eq!(36, a.len());
+    assert_eq!(b'4', a.as_bytes()[14]);
+    assert!(a
+        .bytes()
+        .all(|c| c == b'-' || c.is_ascii_digit() || (b'a'..=b'f').contains(&c)));
+}
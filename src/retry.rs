@@ -0,0 +1,117 @@
+//! Retrying requests that fail for transient reasons.
+
+use std::time::Duration;
+
+use crate::request::random_u64;
+
+/// How a [`Client`](crate::Client) retries requests that fail with a
+/// [retryable](crate::Error::is_retryable) error.
+///
+/// Only requests that are safe to repeat are retried: queries made with
+/// [`Client::query`](crate::Client::query), and inserts that carry an
+/// `insert_deduplication_token`.  Between attempts the client waits for an
+/// exponentially increasing backoff, with random jitter.
+///
+/// By default, requests are not retried.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+/// let policy = streamhouse::RetryPolicy::new(5)
+///     .with_initial_backoff(Duration::from_millis(50))
+///     .with_max_backoff(Duration::from_secs(2));
+/// let client = streamhouse::Client::builder()
+///     .with_url("http://localhost:8123")
+///     .with_retry_policy(policy)
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::new(1)
+    }
+}
+
+impl RetryPolicy {
+    /// Make at most `max_attempts` attempts at each request, including the
+    /// first one.
+    pub fn new(max_attempts: u32) -> Self {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+        }
+    }
+    /// The backoff after the first attempt, which doubles after each further
+    /// attempt.  The default is 100ms.
+    pub fn with_initial_backoff(self, initial_backoff: Duration) -> Self {
+        RetryPolicy {
+            initial_backoff,
+            ..self
+        }
+    }
+    /// The longest backoff between attempts.  The default is 10s.
+    pub fn with_max_backoff(self, max_backoff: Duration) -> Self {
+        RetryPolicy {
+            max_backoff,
+            ..self
+        }
+    }
+    /// Whether to wait for a random time between half of the backoff and the
+    /// full backoff, so that many clients do not retry at the same moment.
+    /// This is enabled by default.
+    pub fn with_jitter(self, jitter: bool) -> Self {
+        RetryPolicy { jitter, ..self }
+    }
+
+    pub(crate) fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// The time to wait after the given attempt, which counts from 1.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32 << attempt.saturating_sub(1).min(31);
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff);
+        if self.jitter {
+            let half = backoff / 2;
+            let nanos = half.as_nanos() as u64;
+            half + Duration::from_nanos(random_u64() % (nanos + 1))
+        } else {
+            backoff
+        }
+    }
+}
+
+#[test]
+fn backoff() {
+    let policy = RetryPolicy::new(10)
+        .with_initial_backoff(Duration::from_millis(100))
+        .with_max_backoff(Duration::from_secs(1))
+        .with_jitter(false);
+    assert_eq!(Duration::from_millis(100), policy.backoff(1));
+    assert_eq!(Duration::from_millis(200), policy.backoff(2));
+    assert_eq!(Duration::from_millis(800), policy.backoff(4));
+    assert_eq!(Duration::from_secs(1), policy.backoff(5));
+    assert_eq!(Duration::from_secs(1), policy.backoff(100));
+
+    let policy = policy.with_jitter(true);
+    for _ in 0..100 {
+        let backoff = policy.backoff(2);
+        assert!(backoff >= Duration::from_millis(100));
+        assert!(backoff <= Duration::from_millis(200));
+    }
+
+    assert_eq!(1, RetryPolicy::default().max_attempts());
+    assert_eq!(1, RetryPolicy::new(0).max_attempts());
+}
@@ -39,7 +39,7 @@ impl<'a> Bytes<'a> {
         }
     }
 
-    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+    pub(crate) fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
         if self.buf.len() < len {
             Err(Error::NotEnoughData)
         } else {
@@ -129,27 +129,6 @@ impl Row for String {
     }
 }
 
-impl Row for Vec<u8> {
-    fn columns(name: &'static str) -> Vec<Column> {
-        vec![Column {
-            name,
-            column_type: "String".to_string(),
-        }]
-    }
-    fn read(buf: &mut Bytes) -> Result<Self, Error> {
-        let l = buf.read_leb128()?;
-        let bytes = buf.read_bytes(l)?;
-        Ok(bytes.to_vec())
-    }
-    fn write(&self, buf: &mut impl WriteRowBinary) -> Result<(), Error> {
-        buf.write_leb128(self.len() as u64)?;
-        for b in self {
-            buf.write_u8(*b)?;
-        }
-        Ok(())
-    }
-}
-
 impl<const N: usize> Row for [u8; N] {
     fn columns(name: &'static str) -> Vec<Column> {
         vec![Column {
@@ -226,12 +205,14 @@ row_via_array!(u16, "UInt16");
 row_via_array!(u32, "UInt32");
 row_via_array!(u64, "UInt64");
 row_via_array!(u128, "UInt128");
+row_via_array!(crate::types::U256, "UInt256");
 
 row_via_array!(i8, "Int8");
 row_via_array!(i16, "Int16");
 row_via_array!(i32, "Int32");
 row_via_array!(i64, "Int64");
 row_via_array!(i128, "Int128");
+row_via_array!(crate::types::I256, "Int256");
 
 row_via_array!(f32, "Float32");
 row_via_array!(f64, "Float64");
@@ -250,25 +231,59 @@ pub(crate) fn single_column<R: Row>() -> String {
     }
 }
 
-impl<T: Row> Row for Box<[T]> {
+/// Implement `Row` as an `Array` for a collection of rows.
+macro_rules! row_via_iter {
+    ($t:ty $(, $bound:path)*) => {
+        impl<T: Row $(+ $bound)*> Row for $t {
+            fn columns(name: &'static str) -> Vec<Column> {
+                vec![Column {
+                    name,
+                    column_type: format!("Array({})", single_column::<T>()),
+                }]
+            }
+            fn read(buf: &mut Bytes) -> Result<Self, Error> {
+                let l = buf.read_leb128()?;
+                (0..l).map(|_| buf.read()).collect()
+            }
+            fn write(&self, buf: &mut impl WriteRowBinary) -> Result<(), Error> {
+                buf.write_leb128(self.len() as u64)?;
+                for v in self.iter() {
+                    v.write(buf)?;
+                }
+                Ok(())
+            }
+        }
+    };
+}
+
+row_via_iter!(Box<[T]>);
+row_via_iter!(Vec<T>);
+row_via_iter!(std::collections::VecDeque<T>);
+row_via_iter!(std::collections::BTreeSet<T>, Ord);
+
+impl<T: Row + Eq + std::hash::Hash> Row for std::collections::HashSet<T> {
     fn columns(name: &'static str) -> Vec<Column> {
-        vec![Column {
-            name,
-            column_type: format!("Array({})", single_column::<T>()),
-        }]
+        Vec::<T>::columns(name)
     }
     fn read(buf: &mut Bytes) -> Result<Self, Error> {
         let l = buf.read_leb128()?;
-        let mut out = Vec::with_capacity(l);
-        for _ in 0..l {
-            out.push(buf.read()?);
-        }
-        Ok(out.into_boxed_slice())
+        (0..l).map(|_| buf.read()).collect()
     }
     fn write(&self, buf: &mut impl WriteRowBinary) -> Result<(), Error> {
-        buf.write_leb128(self.len() as u64)?;
-        for v in self.iter() {
-            v.write(buf)?;
+        // The elements are written in order of their encodings, so that the
+        // same set is always written the same way.
+        let mut encoded = self
+            .iter()
+            .map(|v| {
+                let mut bytes = Vec::new();
+                v.write(&mut bytes)?;
+                Ok(bytes)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        encoded.sort_unstable();
+        buf.write_leb128(encoded.len() as u64)?;
+        for b in encoded.into_iter().flatten() {
+            buf.write_u8(b)?;
         }
         Ok(())
     }
@@ -0,0 +1,214 @@
+//! Inserting rows through a [`futures_util::Sink`].
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures_util::future::BoxFuture;
+
+use crate::compression::{compress_lz4, MAX_UNCOMPRESSED_BLOCK_SIZE};
+use crate::query::{finish, insert_header};
+use crate::request::generate_query_id;
+use crate::summary::{ProgressCallback, ProgressWatch};
+use crate::{Client, Compression, Error, Insert, QuerySummary, Row};
+
+/// The amount of data to collect before sending it to the server.
+const CHUNK_SIZE: usize = MAX_UNCOMPRESSED_BLOCK_SIZE;
+
+/// A [`futures_util::Sink`] that inserts rows into a table, created by
+/// [`Client::insert_sink`].
+///
+/// All rows are sent in the body of a single `INSERT`, which is completed
+/// when the sink is closed.  If the sink is dropped without being closed, the
+/// request is aborted, although any blocks of rows that the server had
+/// already read may have been committed, as described for
+/// [`Client::insert_stream`].  The sink only accepts more rows once the
+/// connection is ready for them, so a slow server slows down the producer
+/// rather than rows piling up in memory.
+///
+/// # Example
+/// ```no_run
+/// # async fn example() -> Result<(), streamhouse::Error> {
+/// # let client = streamhouse::Client::builder().with_url("http://localhost:8123").build();
+/// use futures_util::{stream, SinkExt, StreamExt};
+/// let mut sink = client.insert_sink::<u64>("numbers")?;
+/// stream::iter(0..1_000_000u64).map(Ok).forward(&mut sink).await?;
+/// println!("inserted {} rows", sink.summary().unwrap().written_rows);
+/// # Ok(())
+/// # }
+/// ```
+pub struct InsertSink<R> {
+    sender: Option<hyper::body::Sender>,
+    response: ResponseState,
+    buffer: Vec<u8>,
+    compression: Compression,
+    progress: Option<ProgressCallback>,
+    progress_watch: Option<ProgressWatch>,
+    summary: Option<QuerySummary>,
+    _phantom: std::marker::PhantomData<fn(R)>,
+}
+
+enum ResponseState {
+    Waiting(hyper::client::ResponseFuture),
+    Finishing(BoxFuture<'static, Result<QuerySummary, Error>>),
+    Done,
+}
+
+impl Client {
+    /// Create a [`Sink`](futures_util::Sink) that inserts the rows sent to it
+    /// into a table.
+    ///
+    /// The insert is completed when the sink is closed, after which
+    /// [`InsertSink::summary`] gives the summary of the insert.
+    pub fn insert_sink<R: Row>(&self, insert: impl Into<Insert>) -> Result<InsertSink<R>, Error> {
+        let insert = insert.into();
+        if insert.auto_deduplication_token {
+            return Err(Error::InvalidParams(
+                "insert_sink cannot compute a deduplication token".into(),
+            ));
+        }
+        let buffer = insert_header::<R>(&insert)?;
+        let query_id = insert.query_id.unwrap_or_else(generate_query_id);
+        let (sender, body) = hyper::Body::channel();
+        let request = self
+            .request_builder(&insert.settings, &[], &query_id, true)
+            .body(body)
+            .map_err(|err| Error::InvalidParams(Box::new(err)))?;
+        Ok(InsertSink {
+            sender: Some(sender),
+            response: ResponseState::Waiting(self.client.request(request)),
+            buffer,
+            compression: self.compression,
+            progress_watch: insert
+                .progress
+                .as_ref()
+                .and_then(|progress| progress.watch(self, &query_id)),
+            progress: insert.progress,
+            summary: None,
+            _phantom: std::marker::PhantomData,
+        })
+    }
+}
+
+impl<R> InsertSink<R> {
+    /// The summary of the insert, once the sink has been closed.
+    pub fn summary(&self) -> Option<&QuerySummary> {
+        self.summary.as_ref()
+    }
+
+    /// Drive the request, returning once the server has responded.
+    fn poll_response(&mut self, cx: &mut Context<'_>) -> Poll<Result<QuerySummary, Error>> {
+        loop {
+            match &mut self.response {
+                ResponseState::Waiting(response) => match ready!(Pin::new(response).poll(cx)) {
+                    Ok(response) => {
+                        let progress = self.progress.clone();
+                        self.response = ResponseState::Finishing(Box::pin(async move {
+                            finish(response, progress.as_ref()).await
+                        }));
+                    }
+                    Err(err) => {
+                        self.response = ResponseState::Done;
+                        return Poll::Ready(Err(err.into()));
+                    }
+                },
+                ResponseState::Finishing(finishing) => {
+                    let result = ready!(finishing.as_mut().poll(cx));
+                    self.response = ResponseState::Done;
+                    self.progress_watch = None;
+                    if let Ok(summary) = &result {
+                        self.summary = Some(*summary);
+                    }
+                    return Poll::Ready(result);
+                }
+                ResponseState::Done => {
+                    return Poll::Ready(
+                        self.summary.ok_or_else(|| {
+                            Error::BadResponse("the insert has already failed".into())
+                        }),
+                    )
+                }
+            }
+        }
+    }
+
+    /// The error to return if the server responds before the insert is
+    /// complete.
+    fn poll_early_response(&mut self, cx: &mut Context<'_>) -> Result<(), Error> {
+        match self.poll_response(cx) {
+            Poll::Pending => Ok(()),
+            Poll::Ready(Err(err)) => Err(err),
+            Poll::Ready(Ok(_)) => Err(Error::BadResponse(
+                "the server ended the insert early".into(),
+            )),
+        }
+    }
+
+    /// Send any buffered rows to the server.
+    fn poll_send_buffer(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.poll_early_response(cx)?;
+        if self.buffer.is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+        let Some(sender) = &mut self.sender else {
+            return Poll::Ready(Err(Error::BadResponse("the insert is closed".into())));
+        };
+        if let Err(err) = ready!(sender.poll_ready(cx)) {
+            // The connection failed, and the response may say why.
+            self.poll_early_response(cx)?;
+            return Poll::Ready(Err(err.into()));
+        }
+        let mut chunk = std::mem::take(&mut self.buffer);
+        if self.compression == Compression::Lz4 {
+            chunk = compress_lz4(&chunk);
+        }
+        if sender.try_send_data(chunk.into()).is_err() {
+            return Poll::Ready(Err(Error::BadResponse(
+                "the connection closed during the insert".into(),
+            )));
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R: Row> futures_util::Sink<R> for InsertSink<R> {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let this = self.get_mut();
+        if this.buffer.len() >= CHUNK_SIZE {
+            this.poll_send_buffer(cx)
+        } else {
+            this.poll_early_response(cx)?;
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, row: R) -> Result<(), Error> {
+        row.write(&mut self.get_mut().buffer)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        self.get_mut().poll_send_buffer(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let this = self.get_mut();
+        if this.sender.is_some() {
+            ready!(this.poll_send_buffer(cx))?;
+            // Dropping the sender ends the body, which completes the insert.
+            this.sender = None;
+        }
+        ready!(this.poll_response(cx))?;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<R> Drop for InsertSink<R> {
+    fn drop(&mut self) {
+        // A sink that was not closed must not complete the insert.
+        if let Some(sender) = self.sender.take() {
+            sender.abort();
+        }
+    }
+}
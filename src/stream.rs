@@ -1,43 +1,165 @@
-use crate::{row::Bytes, Error, Row};
-use futures_util::stream::TryStreamExt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 
-pub(crate) struct Stream<R: Row> {
-    body: hyper::Body,
+use crate::error::{
+    find_exception, find_tagged_exception, starts_with_exception, untag_exception,
+    MAX_EXCEPTION_LEN,
+};
+use crate::summary::ProgressWatch;
+use crate::{row::Bytes, Client, Error, Query, QuerySummary, Row};
+use futures_util::StreamExt;
+
+type Body = Pin<Box<dyn futures_util::Stream<Item = Result<hyper::body::Bytes, Error>> + Send>>;
+
+/// The name and type of a column, as reported by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnInfo {
+    name: String,
+    column_type: String,
+}
+
+impl ColumnInfo {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn column_type(&self) -> &str {
+        &self.column_type
+    }
+}
+
+/// A stream of rows returned by [`Client::query`].
+///
+/// This implements [`futures_util::Stream`], and also gives access to
+/// information about the query, such as the columns that the server sent.
+pub struct RowStream<R: Row> {
+    body: Body,
     bytes: Vec<u8>,
     cursor: usize,
     all_done: bool,
+    /// An exception sent by the server after some rows, which is read to
+    /// the end of the body before it is returned.
+    exception: Option<Vec<u8>>,
+    /// The `X-ClickHouse-Exception-Tag` header, which newer servers send to
+    /// mark an exception in the body.
+    exception_tag: Option<String>,
+    columns: Vec<ColumnInfo>,
+    query_id: String,
+    server_timezone: Option<String>,
+    summary: Option<QuerySummary>,
+    kill_on_drop: Option<Client>,
+    _progress: Option<ProgressWatch>,
     _phantom: std::marker::PhantomData<R>,
 }
 
-impl<R: Row> Stream<R> {
-    pub async fn new(body: hyper::Body) -> Result<Self, Error> {
+impl<R: Row> RowStream<R> {
+    pub(crate) async fn new(
+        body: impl futures_util::Stream<Item = Result<hyper::body::Bytes, Error>> + Send + 'static,
+        headers: &hyper::HeaderMap,
+        query_id: String,
+        kill_on_drop: Option<Client>,
+        progress: Option<ProgressWatch>,
+    ) -> Result<Self, Error> {
         let mut s = Self {
-            body,
+            body: Box::pin(body),
             bytes: Vec::new(),
             cursor: 0,
             all_done: false,
+            exception: None,
+            exception_tag: headers
+                .get("X-ClickHouse-Exception-Tag")
+                .and_then(|tag| tag.to_str().ok())
+                .map(String::from),
+            columns: Vec::new(),
+            query_id,
+            server_timezone: headers
+                .get("X-ClickHouse-Timezone")
+                .and_then(|tz| tz.to_str().ok())
+                .map(String::from),
+            summary: QuerySummary::from_headers(headers),
+            kill_on_drop,
+            _progress: progress,
             _phantom: std::marker::PhantomData,
         };
         s.check_header().await?;
         Ok(s)
     }
 
-    fn am_done(&mut self) -> bool {
+    /// The `query_id` of the query, which identifies it in e.g.
+    /// `system.query_log`.
+    pub fn query_id(&self) -> &str {
+        &self.query_id
+    }
+
+    /// The names and types of the columns sent by the server.
+    pub fn columns(&self) -> &[ColumnInfo] {
+        &self.columns
+    }
+
+    /// The timezone of the server, which is used for `DateTime` columns that
+    /// have no explicit timezone.
+    pub fn server_timezone(&self) -> Option<&str> {
+        self.server_timezone.as_deref()
+    }
+
+    /// A summary of the work done by the query.
+    ///
+    /// This is `None` until every row has been read.  Unless the query was run
+    /// with the `wait_end_of_query` setting, clickhouse sends the summary
+    /// before it has finished the query, so it may be incomplete.
+    pub fn summary(&self) -> Option<&QuerySummary> {
+        if self.am_done() {
+            self.summary.as_ref()
+        } else {
+            None
+        }
+    }
+
+    fn am_done(&self) -> bool {
         self.all_done && self.cursor == self.bytes.len()
     }
 
-    pub async fn read<V: Row>(&mut self) -> Result<V, Error> {
+    fn poll_read<V: Row>(&mut self, cx: &mut Context<'_>) -> Poll<Result<V, Error>> {
         loop {
-            let mut buf = Bytes {
-                buf: &self.bytes[self.cursor..],
+            let rest = &self.bytes[self.cursor..];
+            // Without a tag, an exception can only be told apart from a row
+            // that looks like one by where it starts, which is where a row
+            // would, and by being at the end of the body.  Rather than
+            // buffering the whole body to find out, bytes that are too long
+            // to be an exception are taken to be rows.
+            let too_long = rest.len() > MAX_EXCEPTION_LEN;
+            let maybe_exception = match self.exception_tag {
+                Some(_) => Some(false),
+                None if too_long => Some(false),
+                None => starts_with_exception(rest),
             };
-            match V::read(&mut buf) {
-                Ok(v) => {
-                    self.cursor = self.bytes.len() - buf.buf.len();
-                    return Ok(v);
+            let error = if maybe_exception != Some(false) && !self.all_done {
+                // Wait for the end of the body to tell which it is.
+                None
+            } else if maybe_exception == Some(true) {
+                return Poll::Ready(Err(self.take_exception(self.cursor)));
+            } else {
+                let mut buf = Bytes { buf: rest };
+                match V::read(&mut buf) {
+                    Ok(v) => {
+                        self.cursor = self.bytes.len() - buf.buf.len();
+                        return Poll::Ready(Ok(v));
+                    }
+                    // A row that cannot be decoded may be a partial row
+                    // followed by an exception, unless there is too much
+                    // left for that.
+                    Err(Error::NotEnoughData) => Some(Error::NotEnoughData),
+                    Err(e) if too_long => return Poll::Ready(Err(e)),
+                    Err(e) => Some(e),
                 }
-                Err(Error::NotEnoughData) => {
-                    if let Some(more_bytes) = self.body.try_next().await? {
+            };
+            if !self.all_done {
+                match self.body.poll_next_unpin(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Some(Ok(more_bytes))) => {
+                        if let Some(exception) = &mut self.exception {
+                            exception.extend(more_bytes);
+                            continue;
+                        }
                         let buf = &self.bytes[self.cursor..];
                         self.bytes = if buf.is_empty() {
                             more_bytes.to_vec()
@@ -48,40 +170,66 @@ impl<R: Row> Stream<R> {
                             b
                         };
                         self.cursor = 0;
-                    } else {
-                        self.all_done = true;
-                        return Err(Error::NotEnoughData);
+                        // A tagged exception cannot be mistaken for a row, so
+                        // it is split off as soon as it is seen, and any rows
+                        // before it are still returned.
+                        if let Some(tag) = &self.exception_tag {
+                            if let Some(i) = find_tagged_exception(&self.bytes, tag) {
+                                self.exception = Some(self.bytes.split_off(i));
+                            }
+                        }
                     }
+                    Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(e)),
+                    Poll::Ready(None) => self.all_done = true,
                 }
-                Err(e) => return Err(e),
+                continue;
             }
+            // The body has ended, and the rest of it is not a row, so it may
+            // be a partial row followed by an exception.
+            if self.exception.is_some() {
+                return Poll::Ready(Err(self.take_exception(self.bytes.len())));
+            }
+            if let Some(i) = find_exception(rest) {
+                return Poll::Ready(Err(self.take_exception(self.cursor + i)));
+            }
+            return Poll::Ready(Err(error.unwrap_or(Error::NotEnoughData)));
         }
     }
 
-    pub async fn get_next(&mut self) -> Option<Result<R, Error>> {
+    /// The error for the exception that starts at `start` or that was split
+    /// off, discarding any partial row before it.
+    fn take_exception(&mut self, start: usize) -> Error {
+        let exception = match (self.exception.take(), &self.exception_tag) {
+            (Some(exception), Some(tag)) => untag_exception(&exception, tag).to_vec(),
+            (Some(exception), None) => exception,
+            (None, _) => self.bytes[start..].to_vec(),
+        };
+        self.cursor = self.bytes.len();
+        Error::from_exception(&exception)
+    }
+
+    async fn read<V: Row>(&mut self) -> Result<V, Error> {
+        futures_util::future::poll_fn(|cx| self.poll_read(cx)).await
+    }
+
+    fn poll_get_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<R, Error>>> {
         if self.am_done() {
-            None
+            Poll::Ready(None)
         } else {
-            match self.read().await {
-                Ok(r) => Some(Ok(r)),
-                Err(Error::NotEnoughData) => {
+            match self.poll_read(cx) {
+                Poll::Pending => Poll::Pending,
+                Poll::Ready(Ok(r)) => Poll::Ready(Some(Ok(r))),
+                Poll::Ready(Err(Error::NotEnoughData)) => {
                     if self.am_done() {
-                        None
+                        Poll::Ready(None)
                     } else {
-                        Some(Err(Error::NotEnoughData))
+                        Poll::Ready(Some(Err(Error::NotEnoughData)))
                     }
                 }
-                Err(e) => Some(Err(e)),
+                Poll::Ready(Err(e)) => Poll::Ready(Some(Err(e))),
             }
         }
     }
-    async fn next_and_self(mut self) -> Option<(Result<R, Error>, Self)> {
-        let v = self.get_next().await;
-        v.map(|r| (r, self))
-    }
-    pub fn into_stream(self) -> impl futures_util::stream::Stream<Item = Result<R, Error>> {
-        Box::pin(futures_util::stream::unfold(self, Self::next_and_self))
-    }
 
     async fn check_header(&mut self) -> Result<(), Error> {
         let column_names: Box<[String]> = self.read().await?;
@@ -115,6 +263,45 @@ impl<R: Row> Stream<R> {
                 schema: column_types,
             });
         }
+        self.columns = column_names
+            .into_vec()
+            .into_iter()
+            .zip(column_types)
+            .map(|(name, column_type)| ColumnInfo { name, column_type })
+            .collect();
         Ok(())
     }
 }
+
+impl<R: Row> Unpin for RowStream<R> {}
+
+impl<R: Row> futures_util::Stream for RowStream<R> {
+    type Item = Result<R, Error>;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_get_next(cx)
+    }
+}
+
+impl<R: Row> Drop for RowStream<R> {
+    fn drop(&mut self) {
+        // The query is over if its whole body was read, even if not every
+        // row was, or if the server reported an exception.
+        if self.all_done || self.exception.is_some() {
+            return;
+        }
+        if let (Some(client), Ok(runtime)) = (
+            self.kill_on_drop.take(),
+            tokio::runtime::Handle::try_current(),
+        ) {
+            let query_id = std::mem::take(&mut self.query_id);
+            runtime.spawn(async move {
+                if let Ok(query) = Query::new("KILL QUERY WHERE query_id = {query_id:String} ASYNC")
+                    .bind("query_id", &query_id)
+                {
+                    // There is nobody to report a failure to.
+                    client.execute(query).await.ok();
+                }
+            });
+        }
+    }
+}
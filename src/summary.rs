@@ -0,0 +1,198 @@
+//! Statistics that clickhouse reports about a query.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{Client, Query, Row};
+
+/// A summary of the work done by a query, as reported by clickhouse in the
+/// `X-ClickHouse-Summary` response header.
+///
+/// The same statistics are used to report the progress of a query, in which
+/// case they describe the work done so far.
+///
+/// Any statistic that the server did not report is zero.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct QuerySummary {
+    pub read_rows: u64,
+    pub read_bytes: u64,
+    pub written_rows: u64,
+    pub written_bytes: u64,
+    pub total_rows_to_read: u64,
+    pub result_rows: u64,
+    pub result_bytes: u64,
+    pub elapsed_ns: u64,
+}
+
+impl QuerySummary {
+    /// The time the server spent on the query.
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed_ns)
+    }
+
+    /// The statistics of two queries added together.
+    pub(crate) fn combine(&self, other: &QuerySummary) -> QuerySummary {
+        QuerySummary {
+            read_rows: self.read_rows + other.read_rows,
+            read_bytes: self.read_bytes + other.read_bytes,
+            written_rows: self.written_rows + other.written_rows,
+            written_bytes: self.written_bytes + other.written_bytes,
+            total_rows_to_read: self.total_rows_to_read + other.total_rows_to_read,
+            result_rows: self.result_rows + other.result_rows,
+            result_bytes: self.result_bytes + other.result_bytes,
+            elapsed_ns: self.elapsed_ns + other.elapsed_ns,
+        }
+    }
+
+    /// Parse the value of an `X-ClickHouse-Summary` header.
+    ///
+    /// This is a flat JSON object whose values are numbers in strings.
+    /// Unknown keys are ignored, and `None` is returned if the value is not
+    /// such an object.
+    pub(crate) fn parse(header: &str) -> Option<Self> {
+        let mut summary = QuerySummary::default();
+        let inner = header.trim().strip_prefix('{')?.strip_suffix('}')?;
+        for pair in inner.split(',').filter(|p| !p.trim().is_empty()) {
+            let (key, value) = pair.split_once(':')?;
+            let field = match unquote(key)? {
+                "read_rows" => &mut summary.read_rows,
+                "read_bytes" => &mut summary.read_bytes,
+                "written_rows" => &mut summary.written_rows,
+                "written_bytes" => &mut summary.written_bytes,
+                "total_rows_to_read" => &mut summary.total_rows_to_read,
+                "result_rows" => &mut summary.result_rows,
+                "result_bytes" => &mut summary.result_bytes,
+                "elapsed_ns" => &mut summary.elapsed_ns,
+                _ => continue,
+            };
+            *field = unquote(value)?.parse().ok()?;
+        }
+        Some(summary)
+    }
+
+    /// Read the summary from response headers, if there is one.
+    pub(crate) fn from_headers(headers: &hyper::HeaderMap) -> Option<Self> {
+        Self::parse(headers.get("X-ClickHouse-Summary")?.to_str().ok()?)
+    }
+}
+
+/// How often the progress of a running query is read.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A function that is called with the progress of a query.
+#[derive(Clone)]
+pub(crate) struct ProgressCallback(Arc<dyn Fn(&QuerySummary) + Send + Sync>);
+
+impl ProgressCallback {
+    pub(crate) fn new(f: impl Fn(&QuerySummary) + Send + Sync + 'static) -> Self {
+        ProgressCallback(Arc::new(f))
+    }
+
+    pub(crate) fn report(&self, progress: &QuerySummary) {
+        (self.0)(progress)
+    }
+
+    /// Read the progress of the query with the given `query_id` from
+    /// `system.processes` every [`PROGRESS_INTERVAL`], and report it until
+    /// the returned [`ProgressWatch`] is dropped.
+    ///
+    /// The progress is not sent in the `X-ClickHouse-Progress` headers,
+    /// because those are only seen when the response starts, and a long
+    /// query sends more of them than hyper accepts.
+    pub(crate) fn watch(&self, client: &Client, query_id: &str) -> Option<ProgressWatch> {
+        let runtime = tokio::runtime::Handle::try_current().ok()?;
+        let query = Query::new(
+            "SELECT read_rows, read_bytes, written_rows, written_bytes, \
+             total_rows_approx AS total_rows_to_read, \
+             toUInt64(elapsed * 1000000000) AS elapsed_ns \
+             FROM system.processes WHERE query_id = {query_id:String}",
+        )
+        .bind("query_id", &query_id.to_string())
+        .ok()?
+        .with_kill_on_drop(false);
+        let client = client.clone();
+        let callback = self.clone();
+        Some(ProgressWatch(runtime.spawn(async move {
+            loop {
+                tokio::time::sleep(PROGRESS_INTERVAL).await;
+                // The query may not have started, or may have just finished.
+                if let Ok(Some(progress)) = client
+                    .query_optional::<ProcessProgress>(query.clone())
+                    .await
+                {
+                    callback.report(&progress.into());
+                }
+            }
+        })))
+    }
+}
+
+/// Stops reporting the progress of a query when it is dropped.
+pub(crate) struct ProgressWatch(tokio::task::JoinHandle<()>);
+
+impl Drop for ProgressWatch {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// A row of `system.processes`.
+#[derive(Row)]
+struct ProcessProgress {
+    read_rows: u64,
+    read_bytes: u64,
+    written_rows: u64,
+    written_bytes: u64,
+    total_rows_to_read: u64,
+    elapsed_ns: u64,
+}
+
+impl From<ProcessProgress> for QuerySummary {
+    fn from(p: ProcessProgress) -> Self {
+        QuerySummary {
+            read_rows: p.read_rows,
+            read_bytes: p.read_bytes,
+            written_rows: p.written_rows,
+            written_bytes: p.written_bytes,
+            total_rows_to_read: p.total_rows_to_read,
+            elapsed_ns: p.elapsed_ns,
+            ..QuerySummary::default()
+        }
+    }
+}
+
+impl std::fmt::Debug for ProgressCallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ProgressCallback")
+    }
+}
+
+/// Remove surrounding whitespace and any double quotes.
+fn unquote(s: &str) -> Option<&str> {
+    let s = s.trim();
+    match s.strip_prefix('"') {
+        Some(s) => s.strip_suffix('"'),
+        None => Some(s),
+    }
+}
+
+#[test]
+fn parse_summary() {
+    let summary = QuerySummary::parse(
+        r#"{"read_rows":"3","read_bytes":"24","written_rows":"0","written_bytes":"0","total_rows_to_read":"3","result_rows":"0","result_bytes":"0","elapsed_ns":"1500000"}"#,
+    )
+    .unwrap();
+    assert_eq!(3, summary.read_rows);
+    assert_eq!(24, summary.read_bytes);
+    assert_eq!(3, summary.total_rows_to_read);
+    assert_eq!(Duration::from_micros(1500), summary.elapsed());
+
+    // Older servers send fewer keys, and newer ones may add more.
+    let summary = QuerySummary::parse(r#"{"written_rows":"7", "memory_usage":"-10"}"#).unwrap();
+    assert_eq!(7, summary.written_rows);
+    assert_eq!(0, summary.elapsed_ns);
+
+    assert_eq!(None, QuerySummary::parse("read_rows=3"));
+    assert_eq!(None, QuerySummary::parse(r#"{"read_rows":"many"}"#));
+}
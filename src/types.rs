@@ -1,39 +1,460 @@
 //! Wrappers for clickhouse column types
 
+use std::marker::PhantomData;
+
 use crate::Column;
 
 use crate::row::single_column;
 use crate::Row;
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
-pub struct DateTime(u32);
+/// The time zone of a [`DateTime`] or [`DateTime64`] column, which is part of
+/// its column type.
+///
+/// The time zone only affects how clickhouse displays and parses the time,
+/// and not its value, but the column type must match the one the server
+/// reports.  A time zone is declared with a marker type:
+///
+/// ```
+/// use streamhouse::types::{DateTime, TimeZone};
+///
+/// struct Berlin;
+/// impl TimeZone for Berlin {
+///     const NAME: Option<&'static str> = Some("Europe/Berlin");
+/// }
+///
+/// #[derive(streamhouse::Row)]
+/// struct Event {
+///     // A `DateTime('Europe/Berlin')` column.
+///     when: DateTime<Berlin>,
+/// }
+/// ```
+pub trait TimeZone {
+    /// The name of the time zone, or `None` for a column without one.
+    const NAME: Option<&'static str>;
+}
+
+/// The time zone of a column whose type does not name one, in which case
+/// clickhouse uses the server's time zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct DefaultTimeZone;
+
+impl TimeZone for DefaultTimeZone {
+    const NAME: Option<&'static str> = None;
+}
+
+/// The `UTC` time zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Utc;
+
+impl TimeZone for Utc {
+    const NAME: Option<&'static str> = Some("UTC");
+}
+
+/// Implement the standard traits for a time type, without requiring them of
+/// its time zone marker.
+macro_rules! time_traits {
+    ($t:ident $(, $p:ident)?) => {
+        impl<$(const $p: u8,)? Tz> Clone for $t<$($p,)? Tz> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+        impl<$(const $p: u8,)? Tz> Copy for $t<$($p,)? Tz> {}
+        impl<$(const $p: u8,)? Tz> PartialEq for $t<$($p,)? Tz> {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+        impl<$(const $p: u8,)? Tz> Eq for $t<$($p,)? Tz> {}
+        impl<$(const $p: u8,)? Tz> PartialOrd for $t<$($p,)? Tz> {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl<$(const $p: u8,)? Tz> Ord for $t<$($p,)? Tz> {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+        impl<$(const $p: u8,)? Tz> std::hash::Hash for $t<$($p,)? Tz> {
+            fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+                self.0.hash(state)
+            }
+        }
+        impl<$(const $p: u8,)? Tz> std::fmt::Debug for $t<$($p,)? Tz> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_tuple(stringify!($t)).field(&self.0).finish()
+            }
+        }
+    };
+}
+
+/// The column type of a time with the given time zone, such as
+/// `DateTime('UTC')`, given the arguments that come before the time zone.
+fn time_column_type<Tz: TimeZone>(name: &str, args: Option<String>) -> String {
+    match (args, Tz::NAME) {
+        (None, None) => name.to_string(),
+        (None, Some(tz)) => format!("{name}('{tz}')"),
+        (Some(args), None) => format!("{name}({args})"),
+        (Some(args), Some(tz)) => format!("{name}({args}, '{tz}')"),
+    }
+}
+
+fn since_epoch() -> std::time::Duration {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+}
+
+/// A clickhouse `DateTime`, which is the number of seconds since
+/// 1970-01-01 00:00:00 UTC.
+///
+/// The time zone of the column is given by `Tz`, so a
+/// `DateTime('Europe/Berlin')` column needs a `DateTime<Berlin>`, where
+/// `Berlin` is a [`TimeZone`].
+pub struct DateTime<Tz = DefaultTimeZone>(u32, PhantomData<fn() -> Tz>);
+
+time_traits!(DateTime);
 
 impl DateTime {
+    /// The current time, rounded down to a whole second.
+    ///
+    /// For a column with a time zone, use
+    /// `DateTime::now().with_time_zone()`.
     pub fn now() -> Self {
-        DateTime(
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs() as u32,
-        )
+        DateTime::from_timestamp(since_epoch().as_secs() as u32)
+    }
+}
+
+impl<Tz> DateTime<Tz> {
+    /// The time that is `seconds` after 1970-01-01 00:00:00 UTC.
+    pub fn from_timestamp(seconds: u32) -> Self {
+        DateTime(seconds, PhantomData)
+    }
+    /// The number of seconds since 1970-01-01 00:00:00 UTC.
+    pub fn timestamp(self) -> u32 {
+        self.0
+    }
+    /// The same time, in a column with another time zone.
+    pub fn with_time_zone<Tz2>(self) -> DateTime<Tz2> {
+        DateTime::from_timestamp(self.0)
     }
 }
 
-impl Row for DateTime {
+impl<Tz: TimeZone> Row for DateTime<Tz> {
     fn columns(name: &'static str) -> Vec<Column> {
         vec![Column {
             name,
-            column_type: "DateTime".to_string(),
+            column_type: time_column_type::<Tz>("DateTime", None),
         }]
     }
     fn read(buf: &mut crate::row::Bytes) -> Result<Self, crate::Error> {
-        Ok(DateTime(u32::read(buf)?))
+        Ok(DateTime::from_timestamp(u32::read(buf)?))
     }
     fn write(&self, buf: &mut impl crate::WriteRowBinary) -> Result<(), crate::Error> {
         self.0.write(buf)
     }
 }
 
+/// A clickhouse `DateTime64(P)`, which is the number of ticks since
+/// 1970-01-01 00:00:00 UTC, where a tick is `10^-P` seconds.
+///
+/// The precision `P` may be from 0 to 9, so a `DateTime64<3>` counts
+/// milliseconds.  As with [`DateTime`], the time zone of the column is given
+/// by `Tz`.
+///
+/// # Example
+/// ```
+/// use streamhouse::types::{DateTime64, Utc};
+///
+/// #[derive(streamhouse::Row)]
+/// struct Event {
+///     // A `DateTime64(3, 'UTC')` column.
+///     when: DateTime64<3, Utc>,
+/// }
+/// let event = Event {
+///     when: DateTime64::from_ticks(1_700_000_000_123),
+/// };
+/// ```
+pub struct DateTime64<const P: u8, Tz = DefaultTimeZone>(i64, PhantomData<fn() -> Tz>);
+
+time_traits!(DateTime64, P);
+
+impl<const P: u8> DateTime64<P> {
+    /// The current time, rounded down to a whole tick.
+    ///
+    /// For a column with a time zone, use
+    /// `DateTime64::now().with_time_zone()`.
+    pub fn now() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID;
+        let ticks = since_epoch().as_nanos() / 10u128.pow(9 - P as u32);
+        DateTime64::from_ticks(ticks as i64)
+    }
+}
+
+impl<const P: u8, Tz> DateTime64<P, Tz> {
+    const VALID: () = assert!(P <= 9, "invalid DateTime64(P)");
+    /// The number of ticks in a second.
+    const TICKS_PER_SECOND: i64 = 10i64.pow(P as u32);
+
+    /// The time that is `ticks` after 1970-01-01 00:00:00 UTC.
+    pub fn from_ticks(ticks: i64) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID;
+        DateTime64(ticks, PhantomData)
+    }
+    /// The number of ticks since 1970-01-01 00:00:00 UTC.
+    pub fn ticks(self) -> i64 {
+        self.0
+    }
+    /// The same time, in a column with another time zone.
+    pub fn with_time_zone<Tz2>(self) -> DateTime64<P, Tz2> {
+        DateTime64::from_ticks(self.0)
+    }
+    /// The time rounded down to a whole second, or `None` if it is outside
+    /// of [`DateTime`]'s range of 1970 to 2106.
+    pub fn to_date_time(self) -> Option<DateTime<Tz>> {
+        let seconds = self.0.div_euclid(Self::TICKS_PER_SECOND);
+        u32::try_from(seconds).ok().map(DateTime::from_timestamp)
+    }
+}
+
+impl<const P: u8, Tz> From<DateTime<Tz>> for DateTime64<P, Tz> {
+    fn from(value: DateTime<Tz>) -> Self {
+        DateTime64::from_ticks(i64::from(value.0) * Self::TICKS_PER_SECOND)
+    }
+}
+
+impl<const P: u8, Tz: TimeZone> Row for DateTime64<P, Tz> {
+    fn columns(name: &'static str) -> Vec<Column> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID;
+        vec![Column {
+            name,
+            column_type: time_column_type::<Tz>("DateTime64", Some(P.to_string())),
+        }]
+    }
+    fn read(buf: &mut crate::row::Bytes) -> Result<Self, crate::Error> {
+        Ok(DateTime64::from_ticks(i64::read(buf)?))
+    }
+    fn write(&self, buf: &mut impl crate::WriteRowBinary) -> Result<(), crate::Error> {
+        self.0.write(buf)
+    }
+}
+
+/// A clickhouse `Date`, which is the number of days since 1970-01-01.
+///
+/// A `Date` may be between 1970-01-01 and 2149-06-06.  For dates outside
+/// that range, use [`Date32`].
+///
+/// # Example
+/// ```
+/// use streamhouse::types::Date;
+/// let date = Date::from_ymd(2024, 2, 29).unwrap();
+/// assert_eq!((2024, 2, 29), date.to_ymd());
+/// assert_eq!("2024-02-29", date.to_string());
+/// ```
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+pub struct Date(u16);
+
+impl Date {
+    /// The date that is `days` after 1970-01-01.
+    pub fn from_days(days: u16) -> Self {
+        Date(days)
+    }
+    /// The number of days since 1970-01-01.
+    pub fn days(self) -> u16 {
+        self.0
+    }
+    /// The date with the given year, month (from 1) and day (from 1), or
+    /// `None` if there is no such date or it is out of range.
+    pub fn from_ymd(year: i32, month: u32, day: u32) -> Option<Self> {
+        u16::try_from(days_from_ymd(year, month, day)?)
+            .ok()
+            .map(Date)
+    }
+    /// The year, month (from 1) and day (from 1).
+    pub fn to_ymd(self) -> (i32, u32, u32) {
+        ymd_from_days(self.0.into())
+    }
+    /// Today's date in UTC.
+    pub fn today() -> Self {
+        Date::from(DateTime::now())
+    }
+    /// Midnight UTC at the start of the date, or `None` if that is after the
+    /// end of [`DateTime`]'s range in 2106.
+    pub fn to_date_time(self) -> Option<DateTime> {
+        date_time_from_days(self.0.into())
+    }
+}
+
+impl From<DateTime> for Date {
+    /// The date in UTC.
+    fn from(value: DateTime) -> Self {
+        Date((value.0 / SECONDS_PER_DAY) as u16)
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_ymd(self.0.into(), f)
+    }
+}
+
+impl Row for Date {
+    fn columns(name: &'static str) -> Vec<Column> {
+        vec![Column {
+            name,
+            column_type: "Date".to_string(),
+        }]
+    }
+    fn read(buf: &mut crate::row::Bytes) -> Result<Self, crate::Error> {
+        Ok(Date(u16::read(buf)?))
+    }
+    fn write(&self, buf: &mut impl crate::WriteRowBinary) -> Result<(), crate::Error> {
+        self.0.write(buf)
+    }
+}
+
+/// A clickhouse `Date32`, which is the number of days since 1970-01-01, and
+/// may be negative.
+///
+/// A `Date32` may be between 1900-01-01 and 2299-12-31.
+///
+/// # Example
+/// ```
+/// use streamhouse::types::Date32;
+/// let date = Date32::from_ymd(1969, 12, 31).unwrap();
+/// assert_eq!(-1, date.days());
+/// assert_eq!("1969-12-31", date.to_string());
+/// ```
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+pub struct Date32(i32);
+
+impl Date32 {
+    /// The earliest date, 1900-01-01.
+    pub const MIN: Date32 = Date32(-25567);
+    /// The latest date, 2299-12-31.
+    pub const MAX: Date32 = Date32(120529);
+
+    /// The date that is `days` after 1970-01-01, or `None` if it is out of
+    /// range.
+    pub fn from_days(days: i32) -> Option<Self> {
+        (Date32::MIN.0..=Date32::MAX.0)
+            .contains(&days)
+            .then_some(Date32(days))
+    }
+    /// The number of days since 1970-01-01.
+    pub fn days(self) -> i32 {
+        self.0
+    }
+    /// The date with the given year, month (from 1) and day (from 1), or
+    /// `None` if there is no such date or it is out of range.
+    pub fn from_ymd(year: i32, month: u32, day: u32) -> Option<Self> {
+        Date32::from_days(i32::try_from(days_from_ymd(year, month, day)?).ok()?)
+    }
+    /// The year, month (from 1) and day (from 1).
+    pub fn to_ymd(self) -> (i32, u32, u32) {
+        ymd_from_days(self.0.into())
+    }
+    /// Today's date in UTC.
+    pub fn today() -> Self {
+        Date32::from(DateTime::now())
+    }
+    /// Midnight UTC at the start of the date, or `None` if that is outside
+    /// of [`DateTime`]'s range of 1970 to 2106.
+    pub fn to_date_time(self) -> Option<DateTime> {
+        date_time_from_days(self.0.into())
+    }
+}
+
+impl From<DateTime> for Date32 {
+    /// The date in UTC.
+    fn from(value: DateTime) -> Self {
+        Date32((value.0 / SECONDS_PER_DAY) as i32)
+    }
+}
+
+impl From<Date> for Date32 {
+    fn from(value: Date) -> Self {
+        Date32(value.0.into())
+    }
+}
+
+impl std::fmt::Display for Date32 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_ymd(self.0.into(), f)
+    }
+}
+
+impl Row for Date32 {
+    fn columns(name: &'static str) -> Vec<Column> {
+        vec![Column {
+            name,
+            column_type: "Date32".to_string(),
+        }]
+    }
+    fn read(buf: &mut crate::row::Bytes) -> Result<Self, crate::Error> {
+        Ok(Date32(i32::read(buf)?))
+    }
+    fn write(&self, buf: &mut impl crate::WriteRowBinary) -> Result<(), crate::Error> {
+        self.0.write(buf)
+    }
+}
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+fn date_time_from_days(days: i64) -> Option<DateTime> {
+    u32::try_from(days * i64::from(SECONDS_PER_DAY))
+        .ok()
+        .map(DateTime::from_timestamp)
+}
+
+/// The number of days since 1970-01-01 of a date in the proleptic Gregorian
+/// calendar, using Howard Hinnant's `days_from_civil` algorithm.
+fn days_from_ymd(year: i32, month: u32, day: u32) -> Option<i64> {
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+/// The inverse of [`days_from_ymd`].
+pub(crate) fn ymd_from_days(days: i64) -> (i32, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year as i32, month as u32, day as u32)
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn write_ymd(days: i64, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    let (year, month, day) = ymd_from_days(days);
+    write!(f, "{year:04}-{month:02}-{day:02}")
+}
+
 impl Row for std::net::Ipv4Addr {
     fn columns(name: &'static str) -> Vec<Column> {
         vec![Column {
@@ -66,6 +487,481 @@ impl Row for std::net::Ipv6Addr {
     }
 }
 
+/// A clickhouse `Decimal(P, S)`, which is a number with `P` significant
+/// digits, of which `S` come after the decimal point.
+///
+/// The value is stored exactly, as its mantissa: the value multiplied by
+/// `10^S`.  clickhouse stores the mantissa in 32, 64 or 128 bits, depending
+/// on the precision, which may be from 1 to 38.  For larger precisions, use
+/// [`Decimal256`].
+///
+/// With the `rust_decimal` feature, a `Decimal` can be converted to and from
+/// a `rust_decimal::Decimal`.
+///
+/// # Example
+/// ```
+/// use streamhouse::types::Decimal;
+/// let price: Decimal<18, 4> = "12.5".parse()?;
+/// assert_eq!(125_000, price.mantissa());
+/// assert_eq!("12.5000", price.to_string());
+/// # Ok::<(), streamhouse::Error>(())
+/// ```
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+pub struct Decimal<const P: u8, const S: u8>(i128);
+
+impl<const P: u8, const S: u8> Decimal<P, S> {
+    const VALID: () = assert!(P >= 1 && P <= 38 && S <= P, "invalid Decimal(P, S)");
+    /// One more than the largest mantissa.
+    const LIMIT: u128 = 10u128.pow(P as u32);
+
+    /// The decimal whose value is `mantissa / 10^S`, or `None` if the
+    /// mantissa has more than `P` digits.
+    pub fn from_mantissa(mantissa: i128) -> Option<Self> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID;
+        (mantissa.unsigned_abs() < Self::LIMIT).then_some(Decimal(mantissa))
+    }
+    /// The value multiplied by `10^S`.
+    pub fn mantissa(self) -> i128 {
+        self.0
+    }
+}
+
+impl<const P: u8, const S: u8> std::str::FromStr for Decimal<P, S> {
+    type Err = crate::Error;
+    /// Parse a decimal such as `-12.34`, which must not have more than `S`
+    /// significant digits after the decimal point.
+    fn from_str(s: &str) -> Result<Self, crate::Error> {
+        decimal_mantissa(s, S)
+            .and_then(|mantissa| mantissa.parse().ok())
+            .and_then(Decimal::from_mantissa)
+            .ok_or_else(|| crate::Error::InvalidDecimal(s.to_string()))
+    }
+}
+
+/// The mantissa of the decimal `s` with the given scale, such as `-1234` for
+/// `-12.34` with a scale of 2, or `None` if `s` is not a decimal with at most
+/// `scale` significant digits after the decimal point.
+fn decimal_mantissa(s: &str, scale: u8) -> Option<String> {
+    let (sign, digits) = match s.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    // Trailing zeros do not need to fit in the scale.
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > usize::from(scale) {
+        return None;
+    }
+    Some(format!(
+        "{sign}{whole}{fraction:0<width$}",
+        width = usize::from(scale)
+    ))
+}
+
+impl<const P: u8, const S: u8> std::fmt::Display for Decimal<P, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&format_decimal(self.0, S.into()))
+    }
+}
+
+/// The text of a decimal with the given mantissa and scale.
+pub(crate) fn format_decimal(mantissa: impl std::fmt::Display, scale: u32) -> String {
+    let mantissa = mantissa.to_string();
+    let (sign, digits) = match mantissa.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", mantissa.as_str()),
+    };
+    let digits = format!("{digits:0>width$}", width = scale as usize + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - scale as usize);
+    if fraction.is_empty() {
+        format!("{sign}{whole}")
+    } else {
+        format!("{sign}{whole}.{fraction}")
+    }
+}
+
+impl<const P: u8, const S: u8> Row for Decimal<P, S> {
+    fn columns(name: &'static str) -> Vec<Column> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID;
+        vec![Column {
+            name,
+            column_type: format!("Decimal({P}, {S})"),
+        }]
+    }
+    fn read(buf: &mut crate::row::Bytes) -> Result<Self, crate::Error> {
+        Ok(Decimal(match P {
+            ..=9 => i32::read(buf)?.into(),
+            10..=18 => i64::read(buf)?.into(),
+            _ => i128::read(buf)?,
+        }))
+    }
+    fn write(&self, buf: &mut impl crate::WriteRowBinary) -> Result<(), crate::Error> {
+        match P {
+            ..=9 => (self.0 as i32).write(buf),
+            10..=18 => (self.0 as i64).write(buf),
+            _ => self.0.write(buf),
+        }
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl<const P: u8, const S: u8> TryFrom<rust_decimal::Decimal> for Decimal<P, S> {
+    type Error = crate::Error;
+    /// Convert a `rust_decimal::Decimal`, which fails unless it can be
+    /// represented exactly.
+    fn try_from(value: rust_decimal::Decimal) -> Result<Self, crate::Error> {
+        let error = || crate::Error::InvalidDecimal(value.to_string());
+        let value = value.normalize();
+        let scale = value.scale();
+        if scale > u32::from(S) {
+            return Err(error());
+        }
+        let mantissa = 10i128
+            .checked_pow(u32::from(S) - scale)
+            .and_then(|factor| value.mantissa().checked_mul(factor))
+            .ok_or_else(error)?;
+        Decimal::from_mantissa(mantissa).ok_or_else(error)
+    }
+}
+
+#[cfg(feature = "rust_decimal")]
+impl<const P: u8, const S: u8> TryFrom<Decimal<P, S>> for rust_decimal::Decimal {
+    type Error = crate::Error;
+    /// Convert to a `rust_decimal::Decimal`, which fails if the mantissa or
+    /// scale is too large for it.
+    fn try_from(value: Decimal<P, S>) -> Result<Self, crate::Error> {
+        rust_decimal::Decimal::try_from_i128_with_scale(value.0, S.into())
+            .map_err(|_| crate::Error::InvalidDecimal(value.to_string()))
+    }
+}
+
+/// A clickhouse `Decimal(P, S)` with a precision from 39 to 76, which
+/// clickhouse stores in 256 bits.
+///
+/// This is like [`Decimal`], but with an [`I256`] mantissa.
+///
+/// # Example
+/// ```
+/// use streamhouse::types::Decimal256;
+/// let balance: Decimal256<76, 18> = "123456789012345678901234567890.5".parse()?;
+/// assert_eq!("123456789012345678901234567890.500000000000000000", balance.to_string());
+/// # Ok::<(), streamhouse::Error>(())
+/// ```
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+pub struct Decimal256<const P: u8, const S: u8>(I256);
+
+impl<const P: u8, const S: u8> Decimal256<P, S> {
+    const VALID: () = assert!(P >= 39 && P <= 76 && S <= P, "invalid Decimal256(P, S)");
+
+    /// The decimal whose value is `mantissa / 10^S`, or `None` if the
+    /// mantissa has more than `P` digits.
+    pub fn from_mantissa(mantissa: I256) -> Option<Self> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID;
+        let digits = mantissa.to_string().trim_start_matches('-').len();
+        (digits <= usize::from(P)).then_some(Decimal256(mantissa))
+    }
+    /// The value multiplied by `10^S`.
+    pub fn mantissa(self) -> I256 {
+        self.0
+    }
+}
+
+impl<const P: u8, const S: u8> std::str::FromStr for Decimal256<P, S> {
+    type Err = crate::Error;
+    /// Parse a decimal such as `-12.34`, which must not have more than `S`
+    /// significant digits after the decimal point.
+    fn from_str(s: &str) -> Result<Self, crate::Error> {
+        decimal_mantissa(s, S)
+            .and_then(|mantissa| mantissa.parse().ok())
+            .and_then(Decimal256::from_mantissa)
+            .ok_or_else(|| crate::Error::InvalidDecimal(s.to_string()))
+    }
+}
+
+impl<const P: u8, const S: u8> std::fmt::Display for Decimal256<P, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&format_decimal(self.0, S.into()))
+    }
+}
+
+impl<const P: u8, const S: u8> Row for Decimal256<P, S> {
+    fn columns(name: &'static str) -> Vec<Column> {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::VALID;
+        vec![Column {
+            name,
+            column_type: format!("Decimal({P}, {S})"),
+        }]
+    }
+    fn read(buf: &mut crate::row::Bytes) -> Result<Self, crate::Error> {
+        Ok(Decimal256(buf.read()?))
+    }
+    fn write(&self, buf: &mut impl crate::WriteRowBinary) -> Result<(), crate::Error> {
+        self.0.write(buf)
+    }
+}
+
+/// A clickhouse `UInt256`.
+///
+/// This type only converts between the number and its bytes or decimal
+/// text, and does no arithmetic.
+///
+/// # Example
+/// ```
+/// use streamhouse::types::U256;
+/// let balance: U256 = "340282366920938463463374607431768211456".parse()?;
+/// assert_eq!(U256::from(u128::MAX), U256::from_be_bytes({
+///     let mut bytes = [0; 32];
+///     bytes[16..].fill(0xff);
+///     bytes
+/// }));
+/// assert_eq!(1, balance.to_be_bytes()[15]);
+/// # Ok::<(), streamhouse::Error>(())
+/// ```
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct U256([u8; 32]);
+
+/// A clickhouse `Int256`, in two's complement.
+///
+/// This type only converts between the number and its bytes or decimal
+/// text, and does no arithmetic.
+///
+/// # Example
+/// ```
+/// use streamhouse::types::I256;
+/// assert_eq!("-1", I256::from(-1i8).to_string());
+/// assert_eq!([0xff; 32], I256::from(-1i8).to_le_bytes());
+/// ```
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Default)]
+pub struct I256([u8; 32]);
+
+macro_rules! int256_common {
+    ($t:ident) => {
+        impl $t {
+            pub fn from_le_bytes(bytes: [u8; 32]) -> Self {
+                $t(bytes)
+            }
+            pub fn to_le_bytes(self) -> [u8; 32] {
+                self.0
+            }
+            pub fn from_be_bytes(mut bytes: [u8; 32]) -> Self {
+                bytes.reverse();
+                $t(bytes)
+            }
+            pub fn to_be_bytes(self) -> [u8; 32] {
+                let mut bytes = self.0;
+                bytes.reverse();
+                bytes
+            }
+        }
+
+        impl std::fmt::Debug for $t {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{}({self})", stringify!($t))
+            }
+        }
+
+        impl PartialOrd for $t {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+    };
+}
+
+int256_common!(U256);
+int256_common!(I256);
+
+impl U256 {
+    pub const MAX: U256 = U256([0xff; 32]);
+}
+
+impl I256 {
+    pub const MIN: I256 = I256({
+        let mut bytes = [0; 32];
+        bytes[31] = 0x80;
+        bytes
+    });
+    pub const MAX: I256 = I256({
+        let mut bytes = [0xff; 32];
+        bytes[31] = 0x7f;
+        bytes
+    });
+
+    fn is_negative(self) -> bool {
+        self.0[31] & 0x80 != 0
+    }
+}
+
+impl Ord for U256 {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.to_be_bytes().cmp(&other.to_be_bytes())
+    }
+}
+
+impl Ord for I256 {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Flipping the sign bit orders negative numbers first.
+        let key = |n: &I256| {
+            let mut bytes = n.to_be_bytes();
+            bytes[0] ^= 0x80;
+            bytes
+        };
+        key(self).cmp(&key(other))
+    }
+}
+
+/// A 256-bit little-endian number from a shorter one, extending its sign if
+/// it is negative.
+fn extend(le: &[u8], negative: bool) -> [u8; 32] {
+    let mut bytes = [if negative { 0xff } else { 0 }; 32];
+    bytes[..le.len()].copy_from_slice(le);
+    bytes
+}
+
+macro_rules! int256_from {
+    ($t:ident, $($from:ty),*) => {
+        $(
+            impl From<$from> for $t {
+                #[allow(unused_comparisons, clippy::absurd_extreme_comparisons)]
+                fn from(value: $from) -> Self {
+                    $t(extend(&value.to_le_bytes(), value < 0))
+                }
+            }
+        )*
+    };
+}
+
+int256_from!(U256, u8, u16, u32, u64, u128);
+int256_from!(I256, i8, i16, i32, i64, i128, u8, u16, u32, u64, u128);
+
+impl std::fmt::Display for U256 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad_integral(true, "", &limbs_to_string(to_limbs(self.0)))
+    }
+}
+
+impl std::fmt::Display for I256 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let magnitude = if self.is_negative() {
+            negate(self.0)
+        } else {
+            self.0
+        };
+        let digits = limbs_to_string(to_limbs(magnitude));
+        f.pad_integral(!self.is_negative(), "", &digits)
+    }
+}
+
+impl std::str::FromStr for U256 {
+    type Err = crate::Error;
+    fn from_str(s: &str) -> Result<Self, crate::Error> {
+        let digits = s.strip_prefix('+').unwrap_or(s);
+        parse_limbs(digits)
+            .map(|limbs| U256(from_limbs(limbs)))
+            .ok_or_else(|| crate::Error::InvalidInteger(s.to_string()))
+    }
+}
+
+impl std::str::FromStr for I256 {
+    type Err = crate::Error;
+    fn from_str(s: &str) -> Result<Self, crate::Error> {
+        let error = || crate::Error::InvalidInteger(s.to_string());
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let magnitude = I256(from_limbs(parse_limbs(digits).ok_or_else(error)?));
+        match (negative, magnitude.is_negative()) {
+            (false, false) => Ok(magnitude),
+            (true, false) => Ok(I256(negate(magnitude.0))),
+            // The magnitude of `MIN` does not fit, but negates to itself.
+            (true, true) if magnitude == I256::MIN => Ok(magnitude),
+            _ => Err(error()),
+        }
+    }
+}
+
+/// The two's complement negation of a little-endian number.
+fn negate(mut bytes: [u8; 32]) -> [u8; 32] {
+    let mut carry = true;
+    for b in bytes.iter_mut() {
+        let (sum, overflow) = (!*b).overflowing_add(u8::from(carry));
+        *b = sum;
+        carry = overflow;
+    }
+    bytes
+}
+
+/// A little-endian number as 64-bit limbs, least significant first.
+fn to_limbs(bytes: [u8; 32]) -> [u64; 4] {
+    std::array::from_fn(|i| u64::from_le_bytes(bytes[i * 8..i * 8 + 8].try_into().unwrap()))
+}
+
+fn from_limbs(limbs: [u64; 4]) -> [u8; 32] {
+    let mut bytes = [0; 32];
+    for (chunk, limb) in bytes.chunks_mut(8).zip(limbs) {
+        chunk.copy_from_slice(&limb.to_le_bytes());
+    }
+    bytes
+}
+
+/// The decimal digits of an unsigned number.
+fn limbs_to_string(mut limbs: [u64; 4]) -> String {
+    const CHUNK: u64 = 10_000_000_000_000_000_000;
+    let mut chunks = Vec::new();
+    loop {
+        // Divide by CHUNK, from the most significant limb down.
+        let mut remainder = 0u128;
+        for limb in limbs.iter_mut().rev() {
+            let n = (remainder << 64) | u128::from(*limb);
+            *limb = (n / u128::from(CHUNK)) as u64;
+            remainder = n % u128::from(CHUNK);
+        }
+        chunks.push(remainder as u64);
+        if limbs == [0; 4] {
+            break;
+        }
+    }
+    let mut out = chunks.pop().unwrap().to_string();
+    for chunk in chunks.into_iter().rev() {
+        out.push_str(&format!("{chunk:019}"));
+    }
+    out
+}
+
+/// Parse decimal digits as an unsigned number, or `None` if they are not
+/// digits or the number does not fit in 256 bits.
+fn parse_limbs(digits: &str) -> Option<[u64; 4]> {
+    if digits.is_empty() {
+        return None;
+    }
+    let mut limbs = [0u64; 4];
+    for c in digits.chars() {
+        let mut carry = u128::from(c.to_digit(10)?);
+        for limb in limbs.iter_mut() {
+            let n = u128::from(*limb) * 10 + carry;
+            *limb = n as u64;
+            carry = n >> 64;
+        }
+        if carry != 0 {
+            return None;
+        }
+    }
+    Some(limbs)
+}
+
 /// A newtype that enables using clickhouse UUID without a uuid crate dependency.
 #[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
 pub struct Uuid([u8; 16]);
@@ -96,6 +992,120 @@ impl Row for Uuid {
     }
 }
 
+/// An `Array` column.
+///
+/// A `Vec<T>`, `VecDeque<T>`, `HashSet<T>`, `BTreeSet<T>` or `Box<[T]>` may
+/// also be used for an `Array(T)` column.  This wrapper makes it explicit that
+/// a column is an array, such as an `Array<u8>` for an `Array(UInt8)` rather
+/// than a [`ByteString`] for a `String`.
+///
+/// Reading an array into a `HashSet` or `BTreeSet` drops any duplicate
+/// elements, and a `BTreeSet` keeps its elements in sorted order rather than
+/// in the order of the array.  A `HashSet` is written with its elements
+/// ordered by their RowBinary encoding, so that the same set is always
+/// written the same way.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Default)]
+pub struct Array<T>(pub Vec<T>);
+
+impl<T> From<Vec<T>> for Array<T> {
+    fn from(value: Vec<T>) -> Self {
+        Array(value)
+    }
+}
+impl<T> FromIterator<T> for Array<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Array(iter.into_iter().collect())
+    }
+}
+impl<T> std::ops::Deref for Array<T> {
+    type Target = Vec<T>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl<T> std::ops::DerefMut for Array<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T: Row> Row for Array<T> {
+    fn columns(name: &'static str) -> Vec<Column> {
+        Vec::<T>::columns(name)
+    }
+    fn read(buf: &mut crate::row::Bytes) -> Result<Self, crate::Error> {
+        Ok(Array(buf.read()?))
+    }
+    fn write(&self, buf: &mut impl crate::WriteRowBinary) -> Result<(), crate::Error> {
+        self.0.write(buf)
+    }
+}
+
+/// A `String` column holding arbitrary bytes, which need not be UTF-8.
+///
+/// A `Vec<u8>` is an `Array(UInt8)`, like any other `Vec`, so this wrapper
+/// is needed for a `String` column that is not read as a Rust [`String`].
+///
+/// # Example
+/// ```
+/// #[derive(streamhouse::Row)]
+/// struct Blob {
+///     // A `String` column.
+///     data: streamhouse::types::ByteString,
+///     // An `Array(UInt8)` column.
+///     values: Vec<u8>,
+/// }
+/// ```
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Default)]
+pub struct ByteString(pub Vec<u8>);
+
+impl From<Vec<u8>> for ByteString {
+    fn from(value: Vec<u8>) -> Self {
+        ByteString(value)
+    }
+}
+impl From<&[u8]> for ByteString {
+    fn from(value: &[u8]) -> Self {
+        ByteString(value.to_vec())
+    }
+}
+impl From<ByteString> for Vec<u8> {
+    fn from(value: ByteString) -> Self {
+        value.0
+    }
+}
+impl std::ops::Deref for ByteString {
+    type Target = Vec<u8>;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+impl std::ops::DerefMut for ByteString {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Row for ByteString {
+    fn columns(name: &'static str) -> Vec<Column> {
+        vec![Column {
+            name,
+            column_type: "String".to_string(),
+        }]
+    }
+    fn read(buf: &mut crate::row::Bytes) -> Result<Self, crate::Error> {
+        let l = buf.read_leb128()?;
+        Ok(ByteString(buf.read_bytes(l)?.to_vec()))
+    }
+    fn write(&self, buf: &mut impl crate::WriteRowBinary) -> Result<(), crate::Error> {
+        buf.write_leb128(self.0.len() as u64)?;
+        for b in &self.0 {
+            buf.write_u8(*b)?;
+        }
+        Ok(())
+    }
+}
+
 /// Represents a `LowCardinality` version of a type
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub struct LowCardinality<T>(pub T);
@@ -176,3 +1186,174 @@ impl<K: Row + Ord + Eq, V: Row> Row for std::collections::BTreeMap<K, V> {
         Ok(())
     }
 }
+
+#[test]
+fn time_column_types() {
+    struct Berlin;
+    impl TimeZone for Berlin {
+        const NAME: Option<&'static str> = Some("Europe/Berlin");
+    }
+    assert_eq!("DateTime", single_column::<DateTime>());
+    assert_eq!(
+        "DateTime('Europe/Berlin')",
+        single_column::<DateTime<Berlin>>()
+    );
+    assert_eq!("DateTime64(3)", single_column::<DateTime64<3>>());
+    assert_eq!(
+        "DateTime64(6, 'UTC')",
+        single_column::<DateTime64<6, Utc>>()
+    );
+
+    let time = DateTime64::<3, Berlin>::from(DateTime::from_timestamp(1_700_000_000));
+    assert_eq!(1_700_000_000_000, time.ticks());
+    let time = DateTime64::<3, Berlin>::from_ticks(1_700_000_000_999);
+    assert_eq!(
+        Some(1_700_000_000),
+        time.to_date_time().map(|t| t.timestamp())
+    );
+    assert_eq!(None, DateTime64::<3>::from_ticks(-1).to_date_time());
+    assert_eq!(time.ticks(), time.with_time_zone::<Utc>().ticks());
+
+    let before = DateTime::now();
+    let now = DateTime64::<9>::now().with_time_zone::<Berlin>();
+    let after = DateTime::now().with_time_zone::<Berlin>();
+    let now = now.to_date_time().unwrap();
+    assert!(before.timestamp() <= now.timestamp() && now <= after);
+    assert!(DateTime64::<0>::now().ticks() >= i64::from(before.timestamp()));
+}
+
+#[test]
+fn decimals() {
+    let parse = |s: &str| s.parse::<Decimal<9, 2>>().map(|d| d.mantissa()).ok();
+    assert_eq!(Some(1234), parse("12.34"));
+    assert_eq!(Some(-1230), parse("-12.3"));
+    assert_eq!(Some(1200), parse("+12"));
+    assert_eq!(Some(50), parse(".5"));
+    assert_eq!(Some(0), parse("0"));
+    assert_eq!(Some(1234), parse("12.34000"));
+    assert_eq!(Some(999_999_999), parse("9999999.99"));
+    assert_eq!(None, parse("10000000"));
+    assert_eq!(None, parse("12.345"));
+    assert_eq!(None, parse(""));
+    assert_eq!(None, parse("."));
+    assert_eq!(None, parse("-"));
+    assert_eq!(None, parse("1e3"));
+    assert_eq!(None, parse("1.2.3"));
+
+    let show = |m| Decimal::<9, 2>::from_mantissa(m).unwrap().to_string();
+    assert_eq!("12.34", show(1234));
+    assert_eq!("-0.05", show(-5));
+    assert_eq!("0.00", show(0));
+    assert_eq!("7", Decimal::<3, 0>::from_mantissa(7).unwrap().to_string());
+    assert_eq!(None, Decimal::<3, 0>::from_mantissa(1000));
+
+    let max = "9".repeat(38);
+    assert_eq!(max, max.parse::<Decimal<38, 0>>().unwrap().to_string());
+    assert!(format!("1{max}").parse::<Decimal<38, 0>>().is_err());
+
+    assert_eq!("Decimal(18, 4)", single_column::<Decimal<18, 4>>());
+
+    #[cfg(feature = "rust_decimal")]
+    {
+        let d = rust_decimal::Decimal::new(-12340, 3);
+        let ours = Decimal::<18, 4>::try_from(d).unwrap();
+        assert_eq!(-123400, ours.mantissa());
+        assert_eq!(d, rust_decimal::Decimal::try_from(ours).unwrap());
+        assert!(Decimal::<18, 1>::try_from(d).is_err());
+        assert!(Decimal::<3, 2>::try_from(d).is_err());
+        assert!(rust_decimal::Decimal::try_from(Decimal::<38, 30>::default()).is_err());
+    }
+}
+
+#[test]
+fn int256() {
+    assert_eq!("0", U256::default().to_string());
+    assert_eq!(u128::MAX.to_string(), U256::from(u128::MAX).to_string());
+    let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935";
+    assert_eq!(max, U256::MAX.to_string());
+    assert_eq!(U256::MAX, max.parse().unwrap());
+    assert!(
+        "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+            .parse::<U256>()
+            .is_err()
+    );
+    assert!("".parse::<U256>().is_err());
+    assert!("-1".parse::<U256>().is_err());
+    assert!("12a".parse::<U256>().is_err());
+
+    let min = "-57896044618658097711785492504343953926634992332820282019728792003956564819968";
+    assert_eq!(min, I256::MIN.to_string());
+    assert_eq!(I256::MIN, min.parse().unwrap());
+    let max = "57896044618658097711785492504343953926634992332820282019728792003956564819967";
+    assert_eq!(max, I256::MAX.to_string());
+    assert_eq!(I256::MAX, max.parse().unwrap());
+    assert!(min[1..].parse::<I256>().is_err());
+    assert_eq!(I256::MAX, I256::MAX.to_string().parse().unwrap());
+    assert_eq!(
+        I256::from(i128::MIN),
+        i128::MIN.to_string().parse().unwrap()
+    );
+    assert_eq!("-1", I256::from(-1i64).to_string());
+    assert_eq!(I256::default(), "-0".parse().unwrap());
+    assert_eq!(I256::from(-12345i32), "-12345".parse().unwrap());
+    assert_eq!("00042", format!("{:05}", U256::from(42u8)));
+
+    assert!(I256::MIN < I256::from(-1i8));
+    assert!(I256::from(-1i8) < I256::default());
+    assert!(I256::default() < I256::MAX);
+    assert!(U256::from(u128::MAX) < U256::MAX);
+
+    let mut be = [0; 32];
+    be[31] = 1;
+    assert_eq!(U256::from(1u8), U256::from_be_bytes(be));
+    assert_eq!(be, U256::from(1u8).to_be_bytes());
+
+    let d: Decimal256<76, 2> = "-12.5".parse().unwrap();
+    assert_eq!(I256::from(-1250i32), d.mantissa());
+    assert_eq!("-12.50", d.to_string());
+    assert!(format!("1{}", "0".repeat(74))
+        .parse::<Decimal256<76, 2>>()
+        .is_err());
+    assert_eq!("Decimal(50, 10)", single_column::<Decimal256<50, 10>>());
+}
+
+#[test]
+fn array_column_types() {
+    use std::collections::{BTreeSet, HashSet, VecDeque};
+    assert_eq!("Array(String)", single_column::<Vec<String>>());
+    assert_eq!("Array(UInt8)", single_column::<Vec<u8>>());
+    assert_eq!("Array(UInt8)", single_column::<Array<u8>>());
+    assert_eq!("Array(Array(Int32))", single_column::<VecDeque<Vec<i32>>>());
+    assert_eq!("Array(UInt64)", single_column::<HashSet<u64>>());
+    assert_eq!(
+        "Array(Nullable(String))",
+        single_column::<BTreeSet<Option<String>>>()
+    );
+    assert_eq!("String", single_column::<ByteString>());
+
+    let mut buf = Vec::new();
+    vec![1u16, 2].write(&mut buf).unwrap();
+    ByteString::from(&b"ab"[..]).write(&mut buf).unwrap();
+    assert_eq!(b"\x02\x01\x00\x02\x00\x02ab", &buf[..]);
+    let mut bytes = crate::row::Bytes { buf: &buf };
+    let set: BTreeSet<u16> = bytes.read().unwrap();
+    assert_eq!(BTreeSet::from([1, 2]), set);
+    assert_eq!(ByteString(b"ab".to_vec()), bytes.read().unwrap());
+
+    let set: HashSet<u16> = (0..100).collect();
+    let mut buf = Vec::new();
+    set.write(&mut buf).unwrap();
+    let mut sorted = Vec::new();
+    set.iter()
+        .copied()
+        .collect::<BTreeSet<u16>>()
+        .write(&mut sorted)
+        .unwrap();
+    assert_eq!(sorted, buf);
+
+    let mut bytes = crate::row::Bytes {
+        buf: b"\x03\x02\x00\x01\x00\x02\x00",
+    };
+    let set: BTreeSet<u16> = bytes.read().unwrap();
+    assert_eq!(vec![1, 2], set.into_iter().collect::<Vec<_>>());
+}
@@ -9,7 +9,7 @@ macro_rules! prepare_database {
 pub(crate) use {::function_name::named, prepare_database};
 pub(crate) mod _priv {
     const HOST: &str = "localhost:8124";
-    use streamhouse::{Client, ClientBuilder};
+    use streamhouse::{Client, ClientBuilder, Identifier};
 
     pub async fn prepare_database(file_path: &str, fn_name: &str) -> ClientBuilder {
         // let name = make_db_name(file_path, fn_name);
@@ -21,13 +21,214 @@ pub(crate) mod _priv {
         println!("Database is {database}");
 
         let temp = client.clone().build();
-        temp.execute(&format!(r"DROP DATABASE IF EXISTS {database}"))
+        let quoted = Identifier::new(database.as_str()).unwrap();
+        temp.execute(&format!(r"DROP DATABASE IF EXISTS {quoted}"))
             .await
             .unwrap();
-        temp.execute(&format!(r"CREATE DATABASE {database}"))
+        temp.execute(&format!(r"CREATE DATABASE {quoted}"))
             .await
             .unwrap();
 
         client.with_database(database)
     }
 }
+
+/// A fake clickhouse server using an in-memory transport, for tests that do
+/// not need a real server.
+pub(crate) mod fake {
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use hyper::client::connect::{Connected, Connection};
+    use hyper::Uri;
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, ReadBuf};
+
+    /// One end of an in-memory pipe.
+    pub struct Pipe(DuplexStream);
+
+    impl Connection for Pipe {
+        fn connected(&self) -> Connected {
+            Connected::new()
+        }
+    }
+    impl AsyncRead for Pipe {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.0).poll_read(cx, buf)
+        }
+    }
+    impl AsyncWrite for Pipe {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            Pin::new(&mut self.0).poll_write(cx, buf)
+        }
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.0).poll_flush(cx)
+        }
+        fn poll_shutdown(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            Pin::new(&mut self.0).poll_shutdown(cx)
+        }
+    }
+
+    /// A connector to a fake server that gives the same response to every
+    /// request, unless it has a route for the request, and remembers the
+    /// requests it was sent.
+    #[derive(Clone)]
+    pub struct FakeServer {
+        status: u16,
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+        delay: Duration,
+        unfinished: bool,
+        routes: Vec<(String, FakeServer)>,
+        failures: Arc<Mutex<usize>>,
+        failure: Option<Box<FakeServer>>,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FakeServer {
+        pub fn new(body: impl Into<Vec<u8>>) -> Self {
+            FakeServer {
+                status: 200,
+                headers: Vec::new(),
+                body: body.into(),
+                delay: Duration::ZERO,
+                unfinished: false,
+                routes: Vec::new(),
+                failures: Arc::new(Mutex::new(0)),
+                failure: None,
+                requests: Arc::new(Mutex::new(Vec::new())),
+            }
+        }
+        pub fn with_status(self, status: u16) -> Self {
+            FakeServer { status, ..self }
+        }
+        pub fn with_header(mut self, name: &str, value: &str) -> Self {
+            self.headers.push((name.to_string(), value.to_string()));
+            self
+        }
+        /// Wait before responding.
+        pub fn with_delay(self, delay: Duration) -> Self {
+            FakeServer { delay, ..self }
+        }
+        /// Send the body, but not all that was promised, and then leave the
+        /// connection open, as a server that is still working would.
+        pub fn with_unfinished_body(self) -> Self {
+            FakeServer {
+                unfinished: true,
+                ..self
+            }
+        }
+        /// Respond to requests that contain `pattern`, in their URL, headers
+        /// or body, with the status, headers, body and delay of `response`.
+        pub fn with_route(mut self, pattern: &str, response: FakeServer) -> Self {
+            self.routes.push((pattern.to_string(), response));
+            self
+        }
+        /// Respond to the next `count` requests with `response`, before any
+        /// routes.
+        pub fn with_failures(self, count: usize, response: FakeServer) -> Self {
+            FakeServer {
+                failures: Arc::new(Mutex::new(count)),
+                failure: Some(Box::new(response)),
+                ..self
+            }
+        }
+        /// The requests received so far, including headers.
+        pub fn requests(&self) -> Vec<String> {
+            self.requests.lock().unwrap().clone()
+        }
+        /// A client that connects to this server.
+        pub fn client(&self) -> streamhouse::ClientBuilder {
+            streamhouse::Client::builder()
+                .with_url("http://fake")
+                .with_connector(self.clone())
+        }
+    }
+
+    impl tower_service::Service<Uri> for FakeServer {
+        type Response = Pipe;
+        type Error = std::io::Error;
+        type Future = std::future::Ready<Result<Pipe, std::io::Error>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _uri: Uri) -> Self::Future {
+            let (client, mut server) = tokio::io::duplex(1 << 16);
+            let this = self.clone();
+            tokio::spawn(async move {
+                // Read the request headers and body before responding.
+                let mut request = Vec::new();
+                let mut buf = [0; 1024];
+                loop {
+                    let n = server.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        return;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_ascii_lowercase();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        if text[..end].contains("transfer-encoding: chunked") {
+                            if text.ends_with("\r\n0\r\n\r\n") {
+                                break;
+                            }
+                            continue;
+                        }
+                        let length = text
+                            .lines()
+                            .find_map(|l| l.strip_prefix("content-length: "))
+                            .map(|l| l.trim().parse::<usize>().unwrap())
+                            .unwrap_or(0);
+                        if request.len() >= end + 4 + length {
+                            break;
+                        }
+                    }
+                }
+                let request = String::from_utf8_lossy(&request).into_owned();
+                let failing = {
+                    let mut failures = this.failures.lock().unwrap();
+                    let failing = *failures > 0;
+                    *failures = failures.saturating_sub(1);
+                    failing
+                };
+                let response = match &this.failure {
+                    Some(failure) if failing => failure,
+                    _ => this
+                        .routes
+                        .iter()
+                        .find(|(pattern, _)| request.contains(pattern.as_str()))
+                        .map_or(&this, |(_, response)| response),
+                };
+                this.requests.lock().unwrap().push(request);
+                tokio::time::sleep(response.delay).await;
+                let mut header = format!("HTTP/1.1 {} Fake\r\n", response.status);
+                for (name, value) in response.headers.iter() {
+                    header.push_str(&format!("{name}: {value}\r\n"));
+                }
+                let length = response.body.len() + usize::from(response.unfinished);
+                header.push_str(&format!("Content-Length: {length}\r\n\r\n"));
+                // The client may have given up on the request.
+                if server.write_all(header.as_bytes()).await.is_ok() {
+                    server.write_all(&response.body).await.ok();
+                }
+                if response.unfinished {
+                    std::future::pending::<()>().await;
+                }
+            });
+            std::future::ready(Ok(Pipe(client)))
+        }
+    }
+}
@@ -0,0 +1,100 @@
+mod common;
+
+use function_name::named;
+use streamhouse::Compression;
+use streamhouse_derive::Row;
+
+#[named]
+#[tokio::test]
+async fn lz4_query() {
+    let client = common::prepare_database!()
+        .with_compression(Compression::Lz4)
+        .build();
+
+    client
+        .execute(
+            r"CREATE TABLE IF NOT EXISTS test (
+                id UInt64,
+                name String,
+            ) Engine=MergeTree ORDER BY (id);",
+        )
+        .await
+        .unwrap();
+
+    #[derive(Row, Eq, PartialEq, Debug, Clone)]
+    struct ThisRow {
+        id: u64,
+        name: String,
+    }
+    // Enough rows that the response spans several compressed blocks.
+    let rows = (0..100_000)
+        .map(|id| ThisRow {
+            id,
+            name: format!("row number {id}"),
+        })
+        .collect::<Vec<_>>();
+    client
+        .insert::<ThisRow, _>("test", rows.iter())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        rows,
+        client
+            .query_fetch_all::<ThisRow>("select id, name from test ORDER BY id")
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        Vec::<u64>::new(),
+        client
+            .query_fetch_all::<u64>("select id from test where id > 1000000")
+            .await
+            .unwrap()
+    );
+}
+
+#[named]
+#[tokio::test]
+async fn lz4_insert_stream() {
+    let client = common::prepare_database!()
+        .with_compression(Compression::Lz4)
+        .build();
+
+    client
+        .execute(
+            r"CREATE TABLE IF NOT EXISTS test (
+                id UInt64,
+                name String,
+            ) Engine=MergeTree ORDER BY (id);",
+        )
+        .await
+        .unwrap();
+
+    #[derive(Row, Eq, PartialEq, Debug, Clone)]
+    struct ThisRow {
+        id: u64,
+        name: String,
+    }
+    let rows = (0..100_000)
+        .map(|id| ThisRow {
+            id,
+            name: format!("row number {id}"),
+        })
+        .collect::<Vec<_>>();
+    client
+        .insert_stream(
+            "test",
+            futures_util::stream::iter(rows.clone().into_iter().map(Ok)),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(
+        rows,
+        client
+            .query_fetch_all::<ThisRow>("select id, name from test ORDER BY id")
+            .await
+            .unwrap()
+    );
+}
@@ -176,3 +176,115 @@ async fn one_string_column() {
             .unwrap()
     );
 }
+
+#[named]
+#[tokio::test]
+async fn subset_of_columns() {
+    let client = common::prepare_database!().build();
+
+    client
+        .execute(
+            r"CREATE TABLE IF NOT EXISTS test (
+            `select` String,
+            age UInt8 DEFAULT 7,
+            doubled UInt16 MATERIALIZED age * 2,
+       ) Engine=MergeTree ORDER BY (`select`);",
+        )
+        .await
+        .unwrap();
+
+    #[derive(Row)]
+    struct Name {
+        select: String,
+    }
+    client
+        .insert::<Name, _>(
+            "test",
+            &[Name {
+                select: "David".to_string(),
+            }],
+        )
+        .await
+        .unwrap();
+
+    #[derive(Row, Debug, PartialEq)]
+    struct Full {
+        select: String,
+        age: u8,
+        doubled: u16,
+    }
+    assert_eq!(
+        vec![Full {
+            select: "David".to_string(),
+            age: 7,
+            doubled: 14
+        }],
+        client
+            .query_fetch_all::<Full>("select `select`, age, doubled from test")
+            .await
+            .unwrap()
+    );
+}
+
+#[named]
+#[tokio::test]
+async fn awkward_table_names() {
+    let client = common::prepare_database!().build();
+    #[derive(Row, Debug, PartialEq)]
+    struct Value {
+        value: u8,
+    }
+
+    let table = streamhouse::Identifier::new("select.my-table").unwrap();
+    client
+        .execute(&format!(
+            "CREATE TABLE {table} (value UInt8) Engine=MergeTree ORDER BY value"
+        ))
+        .await
+        .unwrap();
+    client
+        .insert(table.clone(), vec![Value { value: 1 }])
+        .await
+        .unwrap();
+    let table_ref = streamhouse::TableRef::new("select.my-table")
+        .unwrap()
+        .with_database("connection__awkward_table_names")
+        .unwrap();
+    client
+        .insert(table_ref, vec![Value { value: 2 }])
+        .await
+        .unwrap();
+    assert_eq!(
+        vec![Value { value: 1 }, Value { value: 2 }],
+        client
+            .query_fetch_all::<Value>(&format!("select value from {table} order by value"))
+            .await
+            .unwrap()
+    );
+}
+
+#[named]
+#[tokio::test]
+async fn query_one() {
+    let client = common::prepare_database!().build();
+    assert_eq!(
+        3,
+        client
+            .query_one::<u64>("select count() from numbers(3)")
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        None,
+        client
+            .query_optional::<u64>("select number from numbers(0)")
+            .await
+            .unwrap()
+    );
+    assert!(matches!(
+        client
+            .query_one::<u64>("select number from numbers(1000000000)")
+            .await,
+        Err(streamhouse::Error::TooManyRows)
+    ));
+}
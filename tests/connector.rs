@@ -0,0 +1,240 @@
+//! Tests using an in-memory transport, which need no clickhouse server.
+
+mod common;
+
+use common::fake::FakeServer;
+use futures_util::StreamExt;
+use streamhouse::{Error, ErrorCode, Row, TableRef};
+
+#[tokio::test]
+async fn in_memory_execute() {
+    let server = FakeServer::new("");
+    let client = server.client().build();
+    client.execute("SELECT 1").await.unwrap();
+    assert!(server.requests()[0].ends_with("\r\n\r\nSELECT 1"));
+}
+
+#[tokio::test]
+async fn in_memory_query() {
+    // A RowBinaryWithNamesAndTypes response with a single UInt8 column.
+    let server = FakeServer::new(&b"\x01\x01x\x05UInt8\x07\x08\x09"[..]);
+    let client = server.client().build();
+    assert_eq!(
+        vec![7u8, 8, 9],
+        client.query_fetch_all::<u8>("SELECT x").await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn query_one_row() {
+    let one = FakeServer::new(&b"\x01\x01x\x05UInt8\x07"[..]);
+    let client = one.client().build();
+    assert_eq!(7, client.query_one::<u8>("SELECT x").await.unwrap());
+    assert_eq!(
+        Some(7),
+        client.query_optional::<u8>("SELECT x").await.unwrap()
+    );
+
+    let none = FakeServer::new(&b"\x01\x01x\x05UInt8"[..]);
+    let client = none.client().build();
+    assert!(matches!(
+        client.query_one::<u8>("SELECT x").await,
+        Err(Error::RowNotFound)
+    ));
+    assert_eq!(None, client.query_optional::<u8>("SELECT x").await.unwrap());
+
+    let many = FakeServer::new(&b"\x01\x01x\x05UInt8\x07\x08\x09"[..]);
+    let client = many.client().build();
+    assert!(matches!(
+        client.query_one::<u8>("SELECT x").await,
+        Err(Error::TooManyRows)
+    ));
+    assert!(matches!(
+        client.query_optional::<u8>("SELECT x").await,
+        Err(Error::TooManyRows)
+    ));
+}
+
+#[tokio::test]
+async fn row_stream_metadata() {
+    let server = FakeServer::new(&b"\x01\x01x\x05UInt8\x07\x08\x09"[..])
+        .with_header("X-ClickHouse-Timezone", "Europe/Paris")
+        .with_header(
+            "X-ClickHouse-Summary",
+            r#"{"read_rows":"3","read_bytes":"3","written_rows":"0","written_bytes":"0","total_rows_to_read":"3","result_rows":"3","result_bytes":"3","elapsed_ns":"1000"}"#,
+        );
+    let client = server.client().build();
+    let mut rows = client.query::<u8>("SELECT x").await.unwrap();
+    assert_eq!(1, rows.columns().len());
+    assert_eq!("x", rows.columns()[0].name());
+    assert_eq!("UInt8", rows.columns()[0].column_type());
+    assert_eq!(Some("Europe/Paris"), rows.server_timezone());
+
+    assert_eq!(None, rows.summary());
+    while rows.next().await.is_some() {}
+    let summary = rows.summary().unwrap();
+    assert_eq!(3, summary.read_rows);
+    assert_eq!(3, summary.result_rows);
+    assert_eq!(1000, summary.elapsed_ns);
+}
+
+#[tokio::test]
+async fn exception_after_rows() {
+    // Two rows, then an exception.
+    let server = FakeServer::new(
+        &b"\x01\x01x\x06String\x01a\x01bCode: 241. DB::Exception: Memory limit exceeded\n"[..],
+    );
+    let client = server.client().build();
+    let mut rows = client.query::<String>("SELECT x").await.unwrap();
+    assert_eq!("a", rows.next().await.unwrap().unwrap());
+    assert_eq!("b", rows.next().await.unwrap().unwrap());
+    match rows.next().await {
+        Some(Err(streamhouse::Error::ServerError { code, message, .. })) => {
+            assert_eq!(ErrorCode::MemoryLimitExceeded, code);
+            assert_eq!("Memory limit exceeded", message);
+        }
+        other => panic!("expected an exception, got {other:?}"),
+    }
+    assert!(rows.next().await.is_none());
+}
+
+#[tokio::test]
+async fn exception_text_in_rows() {
+    // A value that contains the text of an exception is not one.
+    let text = "Code: 241. DB::Exception: Memory limit exceeded";
+    let mut body = b"\x01\x01x\x06String".to_vec();
+    for _ in 0..2 {
+        body.push(text.len() as u8);
+        body.extend(text.as_bytes());
+    }
+    let server = FakeServer::new(body);
+    let client = server.client().build();
+    assert_eq!(
+        vec![text.to_string(), text.to_string()],
+        client.query_fetch_all::<String>("SELECT x").await.unwrap()
+    );
+}
+
+#[tokio::test]
+async fn decode_error_before_end() {
+    // A string that is not UTF-8, followed by more rows than an exception
+    // could take, from a server that has not finished the body.
+    let mut body = b"\x01\x01x\x06String\x02\xff\xfe".to_vec();
+    body.resize(body.len() + (100 << 10), 0);
+    let server = FakeServer::new(body).with_unfinished_body();
+    let client = server.client().build();
+    let mut rows = client.query::<String>("SELECT x").await.unwrap();
+    let first = tokio::time::timeout(std::time::Duration::from_secs(10), rows.next())
+        .await
+        .expect("the error is reported before the body ends");
+    assert!(
+        matches!(first, Some(Err(Error::InvalidUnicode(_)))),
+        "{first:?}"
+    );
+}
+
+#[tokio::test]
+async fn tagged_exception() {
+    // A row, then part of a row, then an exception marked with the tag from
+    // the header.
+    let server = FakeServer::new(
+        &b"\x01\x01x\x06String\x01a\x05ab__exception__\r\nabcdefghijklmnop\r\nCode: 241. DB::Exception: Memory limit exceeded\r\n48 abcdefghijklmnop\r\n__exception__\r\n"[..],
+    )
+    .with_header("X-ClickHouse-Exception-Tag", "abcdefghijklmnop");
+    let client = server.client().build();
+    let mut rows = client.query::<String>("SELECT x").await.unwrap();
+    assert_eq!("a", rows.next().await.unwrap().unwrap());
+    match rows.next().await {
+        Some(Err(streamhouse::Error::ServerError { code, message, .. })) => {
+            assert_eq!(ErrorCode::MemoryLimitExceeded, code);
+            assert_eq!("Memory limit exceeded", message);
+        }
+        other => panic!("expected an exception, got {other:?}"),
+    }
+    assert!(rows.next().await.is_none());
+}
+
+#[tokio::test]
+async fn exception_code_header() {
+    let server = FakeServer::new("Code: 60. DB::Exception: Unknown table")
+        .with_header("X-ClickHouse-Exception-Code", "60");
+    let client = server.client().build();
+    assert!(matches!(
+        client.query::<u8>("SELECT x FROM t").await,
+        Err(streamhouse::Error::ServerError {
+            code: ErrorCode::UnknownTable,
+            http_status: 200,
+            ..
+        })
+    ));
+    assert!(client.execute("DROP TABLE t").await.is_err());
+
+    // The code is taken from the header if the body cannot be parsed.
+    let server = FakeServer::new("oops")
+        .with_status(500)
+        .with_header("X-ClickHouse-Exception-Code", "252");
+    let client = server.client().build();
+    match client.execute("INSERT INTO t VALUES (1)").await {
+        Err(streamhouse::Error::ServerError {
+            code,
+            name,
+            message,
+            http_status,
+        }) => {
+            assert_eq!(ErrorCode::TooManyParts, code);
+            assert_eq!("TOO_MANY_PARTS", name);
+            assert_eq!("oops", message);
+            assert_eq!(500, http_status);
+        }
+        e => panic!("unexpected {e:?}"),
+    }
+
+    let server = FakeServer::new("Bad Gateway").with_status(502);
+    let client = server.client().build();
+    assert!(matches!(
+        client.execute("SELECT 1").await,
+        Err(streamhouse::Error::HttpStatus { status: 502, .. })
+    ));
+}
+
+#[tokio::test]
+async fn quoted_table_names() {
+    #[derive(Row)]
+    struct Value {
+        value: u8,
+    }
+
+    let server = FakeServer::new("");
+    let client = server.client().build();
+    client
+        .insert("events; DROP TABLE users", vec![Value { value: 1 }])
+        .await
+        .unwrap();
+    let table = TableRef::new("page-views")
+        .unwrap()
+        .with_database("analytics.v2")
+        .unwrap();
+    client
+        .insert(table, vec![Value { value: 1 }])
+        .await
+        .unwrap();
+    let requests = server.requests();
+    assert!(requests[0].contains("INSERT INTO `events; DROP TABLE users` (`value`) FORMAT"));
+    assert!(requests[1].contains("INSERT INTO `analytics.v2`.`page-views` (`value`) FORMAT"));
+
+    assert!(matches!(
+        client.insert("", vec![Value { value: 1 }]).await,
+        Err(Error::InvalidIdentifier(_))
+    ));
+    assert!(matches!(
+        client.insert("db.events", vec![Value { value: 1 }]).await,
+        Err(Error::DottedTableName(_))
+    ));
+    assert_eq!(2, server.requests().len());
+    let table = streamhouse::Identifier::new("db.events").unwrap();
+    client
+        .insert(table, vec![Value { value: 1 }])
+        .await
+        .unwrap();
+    assert!(server.requests()[2].contains("INSERT INTO `db.events` (`value`) FORMAT"));
+}
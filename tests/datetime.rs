@@ -1,7 +1,7 @@
 mod common;
 
 use function_name::named;
-use streamhouse::types::DateTime;
+use streamhouse::types::{Date, Date32, DateTime, DateTime64, TimeZone, Utc};
 use streamhouse_derive::Row;
 
 #[named]
@@ -36,3 +36,147 @@ async fn stream_rows() {
             .unwrap()
     );
 }
+
+#[test]
+fn date_conversions() {
+    assert_eq!((1970, 1, 1), Date::from_days(0).to_ymd());
+    assert_eq!((2149, 6, 6), Date::from_days(u16::MAX).to_ymd());
+    assert_eq!(Some(Date::from_days(u16::MAX)), Date::from_ymd(2149, 6, 6));
+    assert_eq!(None, Date::from_ymd(2149, 6, 7));
+    assert_eq!(None, Date::from_ymd(1969, 12, 31));
+    assert_eq!(None, Date::from_ymd(2023, 2, 29));
+    assert_eq!(None, Date::from_ymd(2024, 13, 1));
+    assert_eq!(Some(Date::from_days(19782)), Date::from_ymd(2024, 2, 29));
+
+    assert_eq!(Some(Date32::MIN), Date32::from_ymd(1900, 1, 1));
+    assert_eq!(Some(Date32::MAX), Date32::from_ymd(2299, 12, 31));
+    assert_eq!(None, Date32::from_ymd(1899, 12, 31));
+    assert_eq!(None, Date32::from_days(Date32::MAX.days() + 1));
+    assert_eq!((1900, 3, 1), Date32::from_ymd(1900, 3, 1).unwrap().to_ymd());
+    assert_eq!(
+        "2000-02-29",
+        Date32::from_ymd(2000, 2, 29).unwrap().to_string()
+    );
+
+    for days in [Date32::MIN.days(), -1, 0, 11016, Date32::MAX.days()] {
+        let date = Date32::from_days(days).unwrap();
+        let (y, m, d) = date.to_ymd();
+        assert_eq!(Some(date), Date32::from_ymd(y, m, d));
+    }
+
+    let date = Date::from_ymd(2024, 2, 29).unwrap();
+    let midnight = date.to_date_time().unwrap();
+    assert_eq!(date, Date::from(midnight));
+    assert_eq!(Date32::from(date), Date32::from(midnight));
+    assert_eq!(None, Date::from_days(u16::MAX).to_date_time());
+    assert_eq!(None, Date32::from_days(-1).unwrap().to_date_time());
+    assert_eq!(Date::today(), Date::from(DateTime::now()));
+}
+
+#[named]
+#[tokio::test]
+async fn dates() {
+    let client = common::prepare_database!().build();
+
+    client
+        .execute(
+            r"CREATE TABLE IF NOT EXISTS test (
+            day Date,
+            birthday Date32,
+       ) Engine=MergeTree
+           ORDER BY (day);",
+        )
+        .await
+        .unwrap();
+
+    #[derive(Row, Eq, PartialEq, Debug, Clone)]
+    struct ThisRow {
+        day: Date,
+        birthday: Date32,
+    }
+    let rows = vec![
+        ThisRow {
+            day: Date::from_ymd(2024, 2, 29).unwrap(),
+            birthday: Date32::from_ymd(1912, 6, 23).unwrap(),
+        },
+        ThisRow {
+            day: Date::from_ymd(2149, 6, 6).unwrap(),
+            birthday: Date32::MAX,
+        },
+    ];
+    client.insert("test", rows.clone()).await.unwrap();
+
+    assert_eq!(
+        rows,
+        client
+            .query_fetch_all::<ThisRow>("select * from test order by day")
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        "1912-06-23",
+        client
+            .query_one::<String>(
+                streamhouse::Query::new("select toString({d:Date32})")
+                    .bind("d", &rows[0].birthday)
+                    .unwrap()
+            )
+            .await
+            .unwrap()
+    );
+}
+
+#[named]
+#[tokio::test]
+async fn time_zones_and_precision() {
+    let client = common::prepare_database!().build();
+
+    client
+        .execute(
+            r"CREATE TABLE IF NOT EXISTS test (
+            when DateTime64(3, 'UTC'),
+            local DateTime('Europe/Berlin'),
+            precise DateTime64(9),
+       ) Engine=MergeTree
+           ORDER BY (when);",
+        )
+        .await
+        .unwrap();
+
+    struct Berlin;
+    impl TimeZone for Berlin {
+        const NAME: Option<&'static str> = Some("Europe/Berlin");
+    }
+
+    #[derive(Row, Eq, PartialEq, Debug, Clone)]
+    struct ThisRow {
+        when: DateTime64<3, Utc>,
+        local: DateTime<Berlin>,
+        precise: DateTime64<9>,
+    }
+    let rows = vec![ThisRow {
+        when: DateTime64::from_ticks(1_700_000_000_123),
+        local: DateTime::now().with_time_zone(),
+        precise: DateTime64::now(),
+    }];
+    client.insert("test", rows.clone()).await.unwrap();
+
+    assert_eq!(
+        rows,
+        client
+            .query_fetch_all::<ThisRow>("select * from test")
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        "2023-11-14 22:13:20.123",
+        client
+            .query_one::<String>(
+                streamhouse::Query::new("select toString({t:DateTime64(3, 'UTC')})")
+                    .bind("t", &rows[0].when)
+                    .unwrap()
+            )
+            .await
+            .unwrap()
+    );
+}
@@ -0,0 +1,180 @@
+mod common;
+
+use std::time::Duration;
+
+use common::fake::FakeServer;
+use function_name::named;
+use streamhouse::internal::{Bytes, Column, WriteRowBinary};
+use streamhouse::Row;
+
+#[derive(Row, Debug, PartialEq)]
+struct Value {
+    value: u8,
+}
+
+const SUMMARY: &str = r#"{"written_rows":"2"}"#;
+
+#[tokio::test]
+async fn batches_by_rows() {
+    let server = FakeServer::new("").with_header("X-ClickHouse-Summary", SUMMARY);
+    let client = server.client().build();
+    let mut inserter = client.inserter::<Value>("t").with_max_rows(2);
+
+    let mut summaries = Vec::new();
+    for value in 0..5 {
+        summaries.push(inserter.write(&Value { value }).await.unwrap());
+    }
+    assert_eq!(1, inserter.pending_rows());
+    assert_eq!(
+        vec![false, true, false, true, false],
+        summaries.iter().map(Option::is_some).collect::<Vec<_>>()
+    );
+    assert_eq!(2, summaries[1].unwrap().written_rows);
+    assert!(inserter.end().await.unwrap().is_some());
+
+    let requests = server.requests();
+    assert_eq!(3, requests.len());
+    assert!(requests[0].ends_with(
+        "INSERT INTO `t` (`value`) FORMAT RowBinaryWithNamesAndTypes\n\x01\x05value\x05UInt8\x00\x01"
+    ));
+    assert!(requests[1].ends_with("UInt8\x02\x03"));
+    assert!(requests[2].ends_with("UInt8\x04"));
+}
+
+#[tokio::test]
+async fn batches_by_bytes_and_time() {
+    let server = FakeServer::new("");
+    let client = server.client().build();
+
+    let mut inserter = client.inserter::<Value>("t").with_max_bytes(3);
+    for value in 0..7 {
+        inserter.write(&Value { value }).await.unwrap();
+    }
+    assert_eq!(2, server.requests().len());
+    inserter.end().await.unwrap();
+    assert_eq!(3, server.requests().len());
+
+    let mut inserter = client
+        .inserter::<Value>("t")
+        .with_period(Duration::from_millis(20));
+    inserter.write(&Value { value: 1 }).await.unwrap();
+    assert_eq!(None, inserter.commit().await.unwrap());
+    tokio::time::sleep(Duration::from_millis(30)).await;
+    assert!(inserter.commit().await.unwrap().is_some());
+    assert_eq!(4, server.requests().len());
+
+    // Nothing is sent for an empty batch.
+    inserter.end().await.unwrap();
+    assert_eq!(4, server.requests().len());
+}
+
+#[tokio::test]
+async fn failed_batch_is_kept() {
+    let server = FakeServer::new("").with_route(
+        "UInt8\x00\x01",
+        FakeServer::new("Code: 999. DB::Exception: oops").with_status(500),
+    );
+    let client = server.client().build();
+    let mut inserter = client.inserter::<Value>("t").with_max_rows(2);
+    inserter.write(&Value { value: 0 }).await.unwrap();
+    assert!(inserter.write(&Value { value: 1 }).await.is_err());
+    assert_eq!(2, inserter.pending_rows());
+
+    // New rows go into a new batch, which waits for the failed one.
+    inserter.write(&Value { value: 2 }).await.unwrap();
+    assert!(inserter.write(&Value { value: 3 }).await.is_err());
+    assert_eq!(4, inserter.pending_rows());
+    let requests = server.requests();
+    assert_eq!(2, requests.len());
+    assert!(requests[0].ends_with("UInt8\x00\x01"));
+    assert!(requests[1].ends_with("UInt8\x00\x01"));
+}
+
+#[tokio::test]
+async fn retry_end() {
+    let server = FakeServer::new("")
+        .with_header("X-ClickHouse-Summary", SUMMARY)
+        .with_failures(2, FakeServer::new("Service Unavailable").with_status(503));
+    let client = server.client().build();
+    let mut inserter = client.inserter::<Value>("t").with_max_rows(2);
+    inserter.write(&Value { value: 0 }).await.unwrap();
+    assert!(inserter.write(&Value { value: 1 }).await.is_err());
+    inserter.write(&Value { value: 2 }).await.unwrap();
+
+    // The failed batch is sent first, and fails again, so neither batch is
+    // lost.
+    assert!(inserter.end().await.is_err());
+    assert_eq!(3, inserter.pending_rows());
+    let summary = inserter.end().await.unwrap().unwrap();
+    assert_eq!(4, summary.written_rows);
+    assert_eq!(0, inserter.pending_rows());
+
+    let requests = server.requests();
+    assert_eq!(4, requests.len());
+    assert!(requests[2].ends_with("UInt8\x00\x01"));
+    assert!(requests[3].ends_with("UInt8\x02"));
+}
+
+/// A row that fails to write after writing some of its data.
+struct Broken;
+
+impl Row for Broken {
+    fn columns(_: &'static str) -> Vec<Column> {
+        vec![Column::new("value", "UInt8")]
+    }
+    fn read(_: &mut Bytes) -> Result<Self, streamhouse::Error> {
+        unimplemented!()
+    }
+    fn write(&self, buf: &mut impl WriteRowBinary) -> Result<(), streamhouse::Error> {
+        buf.write_u8(7)?;
+        Err(streamhouse::Error::NotEnoughData)
+    }
+}
+
+#[tokio::test]
+async fn failed_write_is_not_sent() {
+    let server = FakeServer::new("");
+    let client = server.client().build();
+    let mut inserter = client.inserter::<Broken>("t");
+    assert!(inserter.write(&Broken).await.is_err());
+    assert_eq!(0, inserter.pending_rows());
+    assert_eq!(None, inserter.end().await.unwrap());
+    assert!(server.requests().is_empty());
+}
+
+#[tokio::test]
+async fn tokens_per_batch() {
+    let server = FakeServer::new("");
+    let client = server.client().build();
+    let insert = streamhouse::Insert::new("t").with_deduplication_token("abc");
+    let mut inserter = client.inserter::<Value>(insert).with_max_rows(1);
+    for value in 0..2 {
+        inserter.write(&Value { value }).await.unwrap();
+    }
+    let requests = server.requests();
+    assert!(requests[0].contains("insert_deduplication_token=abc-0"));
+    assert!(requests[1].contains("insert_deduplication_token=abc-1"));
+}
+
+#[named]
+#[tokio::test]
+async fn inserter() {
+    let client = common::prepare_database!().build();
+    client
+        .execute("CREATE TABLE test (value UInt8) Engine=MergeTree ORDER BY value")
+        .await
+        .unwrap();
+    let mut inserter = client.inserter::<Value>("test").with_max_rows(3);
+    for value in 0..10 {
+        inserter.write(&Value { value }).await.unwrap();
+    }
+    inserter.end().await.unwrap();
+    let rows = client
+        .query_fetch_all::<Value>("SELECT value FROM test ORDER BY value")
+        .await
+        .unwrap();
+    assert_eq!(
+        (0..10).map(|value| Value { value }).collect::<Vec<_>>(),
+        rows
+    );
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use common::fake::FakeServer;
+use function_name::named;
+use streamhouse::{Query, Row};
+
+#[tokio::test]
+async fn params_in_url() {
+    let server = FakeServer::new("");
+    let client = server.client().build();
+    client
+        .execute(
+            Query::new("SELECT {name:String}, {ids:Array(UInt8)}")
+                .bind("name", &"it's me".to_string())
+                .unwrap()
+                .bind("ids", &vec![1u8, 2].into_boxed_slice())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(server.requests()[0]
+        .starts_with("POST /?param_name=it%27s%20me&param_ids=%5B1%2C2%5D&query_id="));
+
+    assert!(Query::new("SELECT 1").bind("bad name", &1u8).is_err());
+}
+
+#[named]
+#[tokio::test]
+async fn bind_params() {
+    let client = common::prepare_database!().build();
+
+    client
+        .execute(
+            r"CREATE TABLE IF NOT EXISTS test (
+                name String,
+                age UInt8,
+                tags Array(String),
+                nickname Nullable(String),
+            ) Engine=MergeTree ORDER BY (name);",
+        )
+        .await
+        .unwrap();
+
+    #[derive(Row, Eq, PartialEq, Debug, Clone)]
+    struct ThisRow {
+        name: String,
+        age: u8,
+        tags: Box<[String]>,
+        nickname: Option<String>,
+    }
+    let rows = vec![
+        ThisRow {
+            name: "Robert'); DROP TABLE test;--".to_string(),
+            age: 12,
+            tags: vec!["tab\there".to_string(), "it's".to_string()].into_boxed_slice(),
+            nickname: None,
+        },
+        ThisRow {
+            name: "Alice\\".to_string(),
+            age: 40,
+            tags: Vec::new().into_boxed_slice(),
+            nickname: Some("Al\n".to_string()),
+        },
+    ];
+    client.insert::<ThisRow, _>("test", &rows).await.unwrap();
+
+    for row in rows.iter() {
+        assert_eq!(
+            vec![row.clone()],
+            client
+                .query_fetch_all::<ThisRow>(
+                    Query::new(
+                        "SELECT name, age, tags, nickname FROM test
+                         WHERE name = {name:String}
+                         AND age = {age:UInt8}
+                         AND tags = {tags:Array(String)}
+                         AND nickname IS NOT DISTINCT FROM {nickname:Nullable(String)}",
+                    )
+                    .bind("name", &row.name)
+                    .unwrap()
+                    .bind("age", &row.age)
+                    .unwrap()
+                    .bind("tags", &row.tags)
+                    .unwrap()
+                    .bind("nickname", &row.nickname)
+                    .unwrap()
+                )
+                .await
+                .unwrap()
+        );
+    }
+
+    // A whole row may be bound as a tuple.
+    assert_eq!(
+        vec![12u8],
+        client
+            .query_fetch_all::<u8>(
+                Query::new(
+                    "SELECT age FROM test
+                     WHERE (name, age) IN [{row:Tuple(String, UInt8)}]"
+                )
+                .bind("row", &(rows[0].name.clone(), rows[0].age))
+                .unwrap()
+            )
+            .await
+            .unwrap()
+    );
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use common::fake::FakeServer;
+use function_name::named;
+use futures_util::StreamExt;
+use streamhouse::Query;
+
+// A RowBinaryWithNamesAndTypes response with a single UInt8 column.
+const BODY: &[u8] = b"\x01\x01x\x05UInt8\x07\x08\x09";
+
+#[tokio::test]
+async fn query_id_in_url() {
+    let server = FakeServer::new(BODY);
+    let client = server.client().build();
+
+    let mut rows = client
+        .query::<u8>(Query::new("SELECT x").with_query_id("my-query"))
+        .await
+        .unwrap();
+    assert_eq!("my-query", rows.query_id());
+    while rows.next().await.is_some() {}
+
+    let rows = client.query::<u8>("SELECT x").await.unwrap();
+    let generated = rows.query_id().to_string();
+    assert_eq!(36, generated.len());
+
+    let requests = server.requests();
+    assert!(requests[0].starts_with("POST /?query_id=my-query HTTP/1.1"));
+    assert!(requests[1].starts_with(&format!("POST /?query_id={generated} HTTP/1.1")));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn kill_on_drop() {
+    let server = FakeServer::new(BODY);
+    let client = server.client().with_kill_on_drop(true).build();
+
+    // A stream that is read to the end is not killed.
+    let mut rows = client
+        .query::<u8>(Query::new("SELECT x").with_query_id("finished"))
+        .await
+        .unwrap();
+    while rows.next().await.is_some() {}
+    drop(rows);
+
+    // Nor is one that opted out.
+    let mut rows = client
+        .query::<u8>(
+            Query::new("SELECT x")
+                .with_query_id("kept")
+                .with_kill_on_drop(false),
+        )
+        .await
+        .unwrap();
+    rows.next().await.unwrap().unwrap();
+    drop(rows);
+
+    let mut rows = client
+        .query::<u8>(Query::new("SELECT x").with_query_id("dropped"))
+        .await
+        .unwrap();
+    assert_eq!(7, rows.next().await.unwrap().unwrap());
+    drop(rows);
+
+    // The KILL QUERY is sent in the background.
+    for _ in 0..500 {
+        if server.requests().len() > 3 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    let requests = server.requests();
+    assert_eq!(4, requests.len());
+    assert!(requests[3].starts_with("POST /?param_query_id=dropped&query_id="));
+    assert!(requests[3].ends_with("KILL QUERY WHERE query_id = {query_id:String} ASYNC"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn no_kill_after_error() {
+    // A stream that ended with an exception is not killed.
+    let server = FakeServer::new(
+        &b"\x01\x01x\x05UInt8\x07Code: 241. DB::Exception: Memory limit exceeded"[..],
+    );
+    let client = server.client().with_kill_on_drop(true).build();
+
+    let mut rows = client.query::<u8>("SELECT x").await.unwrap();
+    assert_eq!(7, rows.next().await.unwrap().unwrap());
+    assert!(rows.next().await.unwrap().is_err());
+    drop(rows);
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert_eq!(1, server.requests().len());
+
+    // Nor is one whose body ended part way through a row.
+    let server = FakeServer::new(&b"\x01\x01x\x06String\x05ab"[..]);
+    let client = server.client().with_kill_on_drop(true).build();
+    let mut rows = client.query::<String>("SELECT x").await.unwrap();
+    assert!(rows.next().await.unwrap().is_err());
+    drop(rows);
+
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert_eq!(1, server.requests().len());
+}
+
+#[named]
+#[tokio::test]
+async fn query_id() {
+    let client = common::prepare_database!().build();
+
+    let rows = client
+        .query_fetch_all::<String>(Query::new("SELECT queryID()").with_query_id("test-query-id"))
+        .await
+        .unwrap();
+    assert_eq!(vec!["test-query-id".to_string()], rows);
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use std::time::Duration;
+
+use common::fake::FakeServer;
+use streamhouse::{ErrorCode, Insert, RetryPolicy, Row};
+
+#[derive(Row)]
+struct Value {
+    value: u8,
+}
+
+fn policy() -> RetryPolicy {
+    RetryPolicy::new(3).with_initial_backoff(Duration::from_millis(1))
+}
+
+#[tokio::test]
+async fn retry_unavailable() {
+    let server = FakeServer::new("Service Unavailable").with_status(503);
+    let client = server.client().with_retry_policy(policy()).build();
+
+    // Queries are retried.
+    let err = client.query::<u8>("SELECT 1").await.err().unwrap();
+    assert!(err.is_retryable());
+    assert_eq!(3, server.requests().len());
+
+    // Other requests are not, unless they can be deduplicated.
+    assert!(client.execute("SELECT 1").await.is_err());
+    assert_eq!(4, server.requests().len());
+    assert!(client.insert("t", vec![Value { value: 1 }]).await.is_err());
+    assert_eq!(5, server.requests().len());
+    assert!(client
+        .insert(
+            Insert::new("t").with_setting("insert_deduplication_token", "abc"),
+            vec![Value { value: 1 }],
+        )
+        .await
+        .is_err());
+    assert_eq!(8, server.requests().len());
+    // The same body is sent each time.
+    assert_eq!(server.requests()[5], server.requests()[7]);
+}
+
+#[tokio::test]
+async fn retry_server_errors() {
+    let server = FakeServer::new(
+        "Code: 202. DB::Exception: Too many simultaneous queries. (TOO_MANY_SIMULTANEOUS_QUERIES)",
+    )
+    .with_status(500);
+    let client = server.client().with_retry_policy(policy()).build();
+    let err = client.query::<u8>("SELECT 1").await.err().unwrap();
+    assert_eq!(
+        Some(ErrorCode::TooManySimultaneousQueries),
+        err.server_code()
+    );
+    assert!(err.is_retryable());
+    assert_eq!(3, server.requests().len());
+
+    // Errors that will not go away are not retried.
+    let server = FakeServer::new("Code: 62. DB::Exception: Syntax error").with_status(400);
+    let client = server.client().with_retry_policy(policy()).build();
+    assert!(client.query::<u8>("SELEC 1").await.is_err());
+    assert_eq!(1, server.requests().len());
+
+    // By default, nothing is retried.
+    let server = FakeServer::new("Service Unavailable").with_status(503);
+    let client = server.client().build();
+    assert!(client.query::<u8>("SELECT 1").await.is_err());
+    assert_eq!(1, server.requests().len());
+}
+
+#[tokio::test]
+async fn deduplication_tokens() {
+    let server = FakeServer::new("");
+    let client = server.client().build();
+    let rows = || vec![Value { value: 1 }, Value { value: 2 }];
+
+    client
+        .insert(Insert::new("t").with_deduplication_token("batch-1"), rows())
+        .await
+        .unwrap();
+    client
+        .insert(Insert::new("t").with_auto_deduplication_token(), rows())
+        .await
+        .unwrap();
+    client
+        .insert(Insert::new("t").with_auto_deduplication_token(), rows())
+        .await
+        .unwrap();
+    client
+        .insert(
+            Insert::new("t").with_auto_deduplication_token(),
+            vec![Value { value: 3 }],
+        )
+        .await
+        .unwrap();
+
+    let token = |request: &str| {
+        request
+            .split(['?', '&', ' '])
+            .find_map(|p| p.strip_prefix("insert_deduplication_token="))
+            .map(String::from)
+    };
+    let requests = server.requests();
+    assert_eq!(Some("batch-1".to_string()), token(&requests[0]));
+    let auto = token(&requests[1]).unwrap();
+    assert_eq!(32, auto.len());
+    assert_eq!(Some(auto.clone()), token(&requests[2]));
+    assert_ne!(Some(auto), token(&requests[3]));
+
+    assert!(client
+        .insert_stream(
+            Insert::new("t").with_auto_deduplication_token(),
+            futures_util::stream::iter(rows().into_iter().map(Ok)),
+        )
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn no_retry_fire_and_forget() {
+    let server = FakeServer::new("Service Unavailable").with_status(503);
+    let client = server
+        .client()
+        .with_retry_policy(policy())
+        .with_async_insert(streamhouse::AsyncInsert::fire_and_forget())
+        .build();
+    assert!(client
+        .insert(
+            Insert::new("t").with_deduplication_token("abc"),
+            vec![Value { value: 1 }],
+        )
+        .await
+        .is_err());
+    assert_eq!(1, server.requests().len());
+}
+
+#[tokio::test]
+async fn retry_async_insert_with_token() {
+    let server = FakeServer::new("Service Unavailable").with_status(503);
+    let client = server
+        .client()
+        .with_retry_policy(policy())
+        .with_async_insert(streamhouse::AsyncInsert::new())
+        .build();
+    assert!(client
+        .insert(
+            Insert::new("t").with_deduplication_token("abc"),
+            vec![Value { value: 1 }],
+        )
+        .await
+        .is_err());
+    let requests = server.requests();
+    assert!(requests.len() > 1);
+    assert!(requests[0].contains("async_insert_deduplicate=1"));
+
+    // Without a token, the insert is neither retried nor deduplicated.
+    let server = FakeServer::new("Service Unavailable").with_status(503);
+    let client = server
+        .client()
+        .with_retry_policy(policy())
+        .with_async_insert(streamhouse::AsyncInsert::new())
+        .build();
+    assert!(client.insert("t", vec![Value { value: 1 }]).await.is_err());
+    let requests = server.requests();
+    assert_eq!(1, requests.len());
+    assert!(!requests[0].contains("async_insert_deduplicate"));
+}
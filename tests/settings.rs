@@ -0,0 +1,86 @@
+mod common;
+
+use common::fake::FakeServer;
+use std::time::Duration;
+
+use streamhouse::{AsyncInsert, Compression, Insert, Query, Row};
+
+#[derive(Row)]
+struct Value {
+    value: u8,
+}
+
+#[tokio::test]
+async fn settings() {
+    let server = FakeServer::new("");
+    let client = server
+        .client()
+        .with_setting("max_execution_time", 5)
+        .with_setting("join_use_nulls", 1)
+        .build();
+
+    client.execute("SELECT 1").await.unwrap();
+    client
+        .execute(Query::new("SELECT 2").with_setting("max_execution_time", 10))
+        .await
+        .unwrap();
+    client
+        .insert(
+            Insert::new("t").with_setting("insert_quorum", 2),
+            Vec::<Value>::new(),
+        )
+        .await
+        .unwrap();
+
+    let requests = server.requests();
+    assert!(requests[0].starts_with("POST /?max_execution_time=5&join_use_nulls=1&query_id="));
+    assert!(requests[1].starts_with("POST /?join_use_nulls=1&max_execution_time=10&query_id="));
+    assert!(requests[2]
+        .starts_with("POST /?max_execution_time=5&join_use_nulls=1&insert_quorum=2&query_id="));
+}
+
+#[tokio::test]
+async fn compression_settings() {
+    let server = FakeServer::new("");
+    let client = server
+        .client()
+        .with_compression(Compression::Lz4)
+        .with_setting("max_memory_usage", 1_000_000)
+        .build();
+
+    client.execute("SELECT 1").await.unwrap();
+    client.insert("t", Vec::<Value>::new()).await.unwrap();
+
+    let requests = server.requests();
+    assert!(requests[0].starts_with("POST /?compress=1&max_memory_usage=1000000&query_id="));
+    assert!(requests[1]
+        .starts_with("POST /?compress=1&decompress=1&max_memory_usage=1000000&query_id="));
+}
+
+#[tokio::test]
+async fn async_insert_settings() {
+    let server = FakeServer::new("");
+    let client = server
+        .client()
+        .with_async_insert(AsyncInsert::new())
+        .build();
+
+    client.insert("t", Vec::<Value>::new()).await.unwrap();
+    client
+        .insert(
+            Insert::new("t").with_async_insert(
+                AsyncInsert::fire_and_forget()
+                    .with_busy_timeout(Duration::from_millis(250))
+                    .with_max_data_size(1 << 20),
+            ),
+            Vec::<Value>::new(),
+        )
+        .await
+        .unwrap();
+
+    let requests = server.requests();
+    assert!(requests[0].starts_with("POST /?async_insert=1&wait_for_async_insert=1&query_id="));
+    assert!(requests[1].starts_with(
+        "POST /?async_insert=1&wait_for_async_insert=0&async_insert_busy_timeout_ms=250&async_insert_max_data_size=1048576&query_id="
+    ));
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use common::fake::FakeServer;
+use function_name::named;
+use futures_util::{stream, SinkExt, StreamExt};
+use streamhouse::Row;
+
+#[derive(Row, Debug, PartialEq)]
+struct Value {
+    value: u8,
+}
+
+#[tokio::test]
+async fn sink_to_fake_server() {
+    let server = FakeServer::new("").with_header("X-ClickHouse-Summary", r#"{"written_rows":"3"}"#);
+    let client = server.client().build();
+
+    let mut sink = client.insert_sink::<Value>("t").unwrap();
+    sink.send(Value { value: 1 }).await.unwrap();
+    assert_eq!(None, sink.summary());
+    stream::iter([2, 3])
+        .map(|value| Ok(Value { value }))
+        .forward(&mut sink)
+        .await
+        .unwrap();
+    assert_eq!(3, sink.summary().unwrap().written_rows);
+
+    let requests = server.requests();
+    assert_eq!(1, requests.len());
+    assert!(requests[0].contains("transfer-encoding: chunked"));
+    let body = requests[0].split("\r\n\r\n").nth(1).unwrap();
+    assert!(body.contains(
+        "INSERT INTO `t` (`value`) FORMAT RowBinaryWithNamesAndTypes\n\x01\x05value\x05UInt8\x01"
+    ));
+    assert!(body.contains("\x02\x03"));
+}
+
+#[tokio::test]
+async fn sink_error() {
+    let server = FakeServer::new("Code: 60. DB::Exception: Unknown table").with_status(404);
+    let client = server.client().build();
+    let mut sink = client.insert_sink::<Value>("t").unwrap();
+    let result = sink.send(Value { value: 1 }).await;
+    assert!(result.is_err() || sink.close().await.is_err());
+}
+
+#[named]
+#[tokio::test]
+async fn insert_sink() {
+    let client = common::prepare_database!().build();
+    client
+        .execute("CREATE TABLE test (value UInt8) Engine=MergeTree ORDER BY value")
+        .await
+        .unwrap();
+    let mut sink = client.insert_sink::<Value>("test").unwrap();
+    stream::iter(0..100)
+        .map(|value| Ok(Value { value }))
+        .forward(&mut sink)
+        .await
+        .unwrap();
+    assert_eq!(100, sink.summary().unwrap().written_rows);
+    let rows = client
+        .query_fetch_all::<Value>("SELECT value FROM test ORDER BY value")
+        .await
+        .unwrap();
+    assert_eq!(
+        (0..100).map(|value| Value { value }).collect::<Vec<_>>(),
+        rows
+    );
+}
+
+#[tokio::test]
+async fn insert_stream_error() {
+    let server = FakeServer::new("");
+    let client = server.client().build();
+    let rows = stream::iter([
+        Ok(Value { value: 1 }),
+        Ok(Value { value: 2 }),
+        Err(streamhouse::Error::InvalidParams("upstream failure".into())),
+        Ok(Value { value: 3 }),
+    ]);
+    match client.insert_stream("t", rows).await {
+        Err(streamhouse::Error::InvalidParams(e)) => assert_eq!("upstream failure", e.to_string()),
+        other => panic!("expected the upstream error, got {other:?}"),
+    }
+    // The request was aborted, so the server never saw a complete body.
+    tokio::task::yield_now().await;
+    assert!(server.requests().is_empty());
+}
+
+#[named]
+#[tokio::test]
+async fn insert_stream_abort() {
+    let client = common::prepare_database!().build();
+    client
+        .execute("CREATE TABLE test (value UInt8) Engine=MergeTree ORDER BY value")
+        .await
+        .unwrap();
+    let rows = stream::iter(0..100).map(|value| {
+        if value < 50 {
+            Ok(Value { value })
+        } else {
+            Err(streamhouse::Error::RowNotFound)
+        }
+    });
+    assert!(matches!(
+        client.insert_stream("test", rows).await,
+        Err(streamhouse::Error::RowNotFound)
+    ));
+    assert_eq!(
+        vec![0u64],
+        client
+            .query_fetch_all::<u64>("SELECT count() FROM test")
+            .await
+            .unwrap()
+    );
+}
@@ -0,0 +1,151 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use common::fake::FakeServer;
+use function_name::named;
+use streamhouse::{Insert, Query, Row};
+
+#[derive(Row)]
+struct Value {
+    value: u8,
+}
+
+#[tokio::test]
+async fn summary_from_headers() {
+    let server = FakeServer::new("").with_header(
+        "X-ClickHouse-Summary",
+        r#"{"read_rows":"0","read_bytes":"0","written_rows":"2","written_bytes":"2","total_rows_to_read":"0","result_rows":"2","result_bytes":"2","elapsed_ns":"5000"}"#,
+    );
+    let client = server.client().build();
+
+    let summary = client.execute("SELECT 1").await.unwrap();
+    assert_eq!(2, summary.written_rows);
+    let summary = client
+        .insert("t", vec![Value { value: 1 }, Value { value: 2 }])
+        .await
+        .unwrap();
+    assert_eq!(2, summary.written_rows);
+    assert_eq!(5000, summary.elapsed_ns);
+
+    // A server that sends no summary gives an empty one.
+    let server = FakeServer::new("");
+    let client = server.client().build();
+    let summary = client.execute("SELECT 1").await.unwrap();
+    assert_eq!(streamhouse::QuerySummary::default(), summary);
+}
+
+/// A row of `system.processes`, as read for the progress of a query.
+fn process_progress(read_rows: u64) -> Vec<u8> {
+    let columns = [
+        "read_rows",
+        "read_bytes",
+        "written_rows",
+        "written_bytes",
+        "total_rows_to_read",
+        "elapsed_ns",
+    ];
+    let mut body = vec![columns.len() as u8];
+    for name in columns {
+        body.push(name.len() as u8);
+        body.extend(name.as_bytes());
+    }
+    for _ in columns {
+        body.extend(b"\x06UInt64");
+    }
+    body.extend(read_rows.to_le_bytes());
+    body.extend([0; 5 * 8]);
+    body
+}
+
+#[tokio::test]
+async fn progress_callback() {
+    let server = FakeServer::new("")
+        .with_delay(Duration::from_millis(1500))
+        .with_header(
+            "X-ClickHouse-Summary",
+            r#"{"read_rows":"3","total_rows_to_read":"3"}"#,
+        )
+        .with_route("system.processes", FakeServer::new(process_progress(1)));
+    let client = server.client().build();
+
+    // The progress is read while the query runs, and then the summary is
+    // reported.
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let s = seen.clone();
+    client
+        .execute(Query::new("SELECT 1").with_progress(move |p| {
+            s.lock().unwrap().push(p.read_rows);
+        }))
+        .await
+        .unwrap();
+    assert_eq!(vec![1, 3], *seen.lock().unwrap());
+
+    let requests = server.requests();
+    assert_eq!(2, requests.len());
+    assert!(!requests[0].contains("send_progress_in_http_headers"));
+    assert!(requests[1].contains("FROM system.processes WHERE query_id = {query_id:String}"));
+    let query_id = requests[0].split("query_id=").nth(1).unwrap();
+    let query_id = &query_id[..query_id.find(' ').unwrap()];
+    assert!(requests[1].contains(&format!("param_query_id={query_id}")));
+
+    let server = FakeServer::new("").with_header(
+        "X-ClickHouse-Summary",
+        r#"{"written_rows":"0","total_rows_to_read":"7"}"#,
+    );
+    let client = server.client().build();
+    let s = seen.clone();
+    client
+        .insert(
+            Insert::new("t").with_progress(move |p| {
+                s.lock().unwrap().push(p.total_rows_to_read);
+            }),
+            Vec::<Value>::new(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(vec![1, 3, 7], *seen.lock().unwrap());
+}
+
+#[tokio::test]
+async fn many_progress_headers() {
+    // A long query that asked for progress headers would get one every
+    // 100ms, which is more than hyper accepts.
+    let mut long_query = FakeServer::new("");
+    for i in 0..150 {
+        long_query = long_query.with_header(
+            "X-ClickHouse-Progress",
+            &format!(r#"{{"read_rows":"{i}"}}"#),
+        );
+    }
+    let server = FakeServer::new("").with_route("send_progress_in_http_headers=1", long_query);
+    let client = server.client().build();
+
+    client
+        .execute(Query::new("SELECT 1").with_progress(|_| {}))
+        .await
+        .unwrap();
+    client
+        .insert(Insert::new("t").with_progress(|_| {}), Vec::<Value>::new())
+        .await
+        .unwrap();
+}
+
+#[named]
+#[tokio::test]
+async fn insert_summary() {
+    let client = common::prepare_database!().build();
+    client
+        .execute("CREATE TABLE test (value UInt8) Engine=MergeTree ORDER BY value")
+        .await
+        .unwrap();
+    let summary = client
+        .insert(
+            "test",
+            (0..10).map(|value| Value { value }).collect::<Vec<_>>(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(10, summary.written_rows);
+}
@@ -1,7 +1,10 @@
 mod common;
 
 use function_name::named;
-use streamhouse::{types::LowCardinality, Row};
+use streamhouse::{
+    types::{Array, ByteString, LowCardinality},
+    Row,
+};
 
 #[named]
 #[tokio::test]
@@ -39,7 +42,13 @@ async fn fetch_all() {
             triple Tuple(UUID, IPv4, Enum('Hello' = 1, 'Goodbye' = 2, 'Adios' = 3)),
             mappy Map(String,UInt64),
             bmappy Map(String,String),
-            greeting Enum('Hello' = 1, 'Goodbye' = 2, 'Adios' = 3)
+            greeting Enum('Hello' = 1, 'Goodbye' = 2, 'Adios' = 3),
+            string_vec Array(String),
+            byte_vec Array(UInt8),
+            deque Array(Array(Int32)),
+            hash_set Array(UInt64),
+            btree_set Array(String),
+            array Array(Nullable(UInt8))
        ) Engine=MergeTree
            ORDER BY (f32);",
         )
@@ -70,7 +79,7 @@ async fn fetch_all() {
         i128: i128,
         string: String,
         low_string: LowCardinality<String>,
-        bytes: Vec<u8>,
+        bytes: ByteString,
         ipv4: std::net::Ipv4Addr,
         ipv6: std::net::Ipv6Addr,
         uuid: streamhouse::types::Uuid,
@@ -82,8 +91,14 @@ async fn fetch_all() {
         tuple: (Greeting, i32),
         triple: (streamhouse::types::Uuid, std::net::Ipv4Addr, Greeting),
         mappy: std::collections::HashMap<String, u64>,
-        bmappy: std::collections::BTreeMap<String, Vec<u8>>,
+        bmappy: std::collections::BTreeMap<String, ByteString>,
         greeting: Greeting,
+        string_vec: Vec<String>,
+        byte_vec: Vec<u8>,
+        deque: std::collections::VecDeque<Vec<i32>>,
+        hash_set: std::collections::HashSet<u64>,
+        btree_set: std::collections::BTreeSet<String>,
+        array: Array<Option<u8>>,
     }
     let rows = vec![AllTypes {
         f32: 137.0,
@@ -101,7 +116,7 @@ async fn fetch_all() {
         i128: 0,
         string: "Hello world".to_string(),
         low_string: "David".to_string().into(),
-        bytes: b"Hello world\0".to_vec(),
+        bytes: b"Hello world\0".to_vec().into(),
         ipv4: std::net::Ipv4Addr::new(127, 0, 0, 1),
         ipv6: std::net::Ipv6Addr::new(0, 0, 0, 0, 0, 0xffff, 0xc00a, 0x2ff),
         uuid: streamhouse::types::Uuid::from([5; 16]),
@@ -123,9 +138,15 @@ async fn fetch_all() {
             .collect(),
         bmappy: ["Hello", "world", "good", "day"]
             .iter()
-            .map(|s| (s.to_string(), s.to_ascii_lowercase().as_bytes().to_vec()))
+            .map(|s| (s.to_string(), s.to_ascii_lowercase().as_bytes().into()))
             .collect(),
         greeting: Greeting::Adios,
+        string_vec: vec!["David".to_string(), "Roundy".to_string()],
+        byte_vec: b"\0\xff".to_vec(),
+        deque: [vec![1, -2], vec![], vec![3]].into(),
+        hash_set: [1, 10, 100].into(),
+        btree_set: ["a".to_string(), "b".to_string()].into(),
+        array: vec![Some(1), None].into(),
         tuple: (Greeting::Goodbye, 137),
         triple: (
             streamhouse::types::Uuid::from([7; 16]),
@@ -144,3 +165,107 @@ async fn fetch_all() {
             .unwrap()
     );
 }
+
+#[named]
+#[tokio::test]
+async fn decimals() {
+    use streamhouse::types::Decimal;
+    let client = common::prepare_database!().build();
+
+    client
+        .execute(
+            r"CREATE TABLE IF NOT EXISTS test (
+            small Decimal(9, 2),
+            price Decimal(18, 4),
+            large Decimal(38, 10),
+       ) Engine=MergeTree
+           ORDER BY (price);",
+        )
+        .await
+        .unwrap();
+
+    #[derive(Row, PartialEq, Debug, Clone)]
+    struct Decimals {
+        small: Decimal<9, 2>,
+        price: Decimal<18, 4>,
+        large: Decimal<38, 10>,
+    }
+    let rows = vec![Decimals {
+        small: "-1234567.89".parse().unwrap(),
+        price: "12.5".parse().unwrap(),
+        large: "1234567890123456789012345678.0123456789".parse().unwrap(),
+    }];
+    client.insert("test", rows.clone()).await.unwrap();
+
+    assert_eq!(
+        rows,
+        client
+            .query_fetch_all::<Decimals>("select * from test")
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        "12.5",
+        client
+            .query_one::<String>("select toString(price) from test")
+            .await
+            .unwrap()
+    );
+}
+
+#[named]
+#[tokio::test]
+async fn int256() {
+    use streamhouse::types::{Decimal256, I256, U256};
+    let client = common::prepare_database!().build();
+
+    client
+        .execute(
+            r"CREATE TABLE IF NOT EXISTS test (
+            balance UInt256,
+            delta Int256,
+            amount Decimal(76, 18),
+       ) Engine=MergeTree
+           ORDER BY (balance);",
+        )
+        .await
+        .unwrap();
+
+    #[derive(Row, PartialEq, Debug, Clone)]
+    struct Balances {
+        balance: U256,
+        delta: I256,
+        amount: Decimal256<76, 18>,
+    }
+    let rows = vec![
+        Balances {
+            balance: U256::MAX,
+            delta: I256::MIN,
+            amount: "-123456789012345678901234567890.123456789012345678"
+                .parse()
+                .unwrap(),
+        },
+        Balances {
+            balance: U256::from(7u8),
+            delta: I256::from(-1i8),
+            amount: Decimal256::default(),
+        },
+    ];
+    client.insert("test", rows.clone()).await.unwrap();
+
+    let mut fetched = client
+        .query_fetch_all::<Balances>("select * from test")
+        .await
+        .unwrap();
+    fetched.sort_by_key(|b| b.balance);
+    let mut expected = rows;
+    expected.sort_by_key(|b| b.balance);
+    assert_eq!(expected, fetched);
+    assert_eq!(
+        U256::MAX.to_string(),
+        client
+            .query_one::<String>("select toString(max(balance)) from test")
+            .await
+            .unwrap()
+    );
+}